@@ -1,53 +1,338 @@
+use std::{
+    fmt::{Display, Write as _},
+    str::FromStr,
+};
+
 use nom::{character::complete::space1, multi::separated_list1};
 
 use crate::{parse_input, parse_number};
 
+/// The direction the levels of a floor must move in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trend {
+    Any,
+    Increasing,
+    Decreasing,
+}
+
+/// The rules deciding whether a floor is safe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SafetyPolicy {
+    pub min_step: usize,
+    pub max_step: usize,
+    pub allow_plateaus: bool,
+    pub trend: Trend,
+}
+
+/// Why a floor was judged unsafe. The index is that of the first level in the offending window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    Plateau { index: usize },
+    StepTooSmall { index: usize, step: usize },
+    StepTooLarge { index: usize, step: usize },
+    DirectionChanged { index: usize },
+    WrongDirection { index: usize },
+}
+
+/// The verdict for a single floor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Safe,
+    /// Safe once the levels at these indices are removed
+    Dampened {
+        violation: Violation,
+        removed: Vec<usize>,
+    },
+    Unsafe(Violation),
+}
+
+impl Default for SafetyPolicy {
+    /// The rules from the puzzle: strictly increasing or decreasing by 1 to 3
+    fn default() -> Self {
+        Self {
+            min_step: 1,
+            max_step: 3,
+            allow_plateaus: false,
+            trend: Trend::Any,
+        }
+    }
+}
+
+impl SafetyPolicy {
+    /// Check if moving from level `a` to level `b` is allowed when the floor follows `trend`
+    fn allows_step(&self, a: usize, b: usize, trend: Trend) -> bool {
+        if a == b {
+            return self.allow_plateaus;
+        }
+        let direction_ok = match trend {
+            Trend::Any => true,
+            Trend::Increasing => a < b,
+            Trend::Decreasing => a > b,
+        };
+        direction_ok && (self.min_step..=self.max_step).contains(&a.abs_diff(b))
+    }
+
+    /// The trends a floor may follow under this policy
+    fn trends(&self) -> &'static [Trend] {
+        match self.trend {
+            Trend::Any => &[Trend::Increasing, Trend::Decreasing],
+            Trend::Increasing => &[Trend::Increasing],
+            Trend::Decreasing => &[Trend::Decreasing],
+        }
+    }
+
+    /// Find the first window of the floor that breaks the policy
+    fn first_violation(&self, floor: &[usize]) -> Option<Violation> {
+        let mut trend = self.trend;
+        for (index, window) in floor.windows(2).enumerate() {
+            let (a, b) = (window[0], window[1]);
+            if a == b {
+                if self.allow_plateaus {
+                    continue;
+                }
+                return Some(Violation::Plateau { index });
+            }
+            let step_trend = if a < b {
+                Trend::Increasing
+            } else {
+                Trend::Decreasing
+            };
+            if trend == Trend::Any {
+                // The first step decides the direction for the rest of the floor
+                trend = step_trend;
+            } else if trend != step_trend {
+                return Some(if self.trend == Trend::Any {
+                    Violation::DirectionChanged { index }
+                } else {
+                    Violation::WrongDirection { index }
+                });
+            }
+            let step = a.abs_diff(b);
+            if step < self.min_step {
+                return Some(Violation::StepTooSmall { index, step });
+            }
+            if step > self.max_step {
+                return Some(Violation::StepTooLarge { index, step });
+            }
+        }
+        None
+    }
+}
+
+/// Parse a policy such as `min=1,max=3,plateaus,trend=increasing`.
+/// Anything not given keeps the value from the default policy.
+impl FromStr for SafetyPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut policy = Self::default();
+        for setting in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match setting.split_once('=') {
+                Some(("min", value)) => {
+                    policy.min_step = value.parse().map_err(|e| format!("min: {e}"))?;
+                }
+                Some(("max", value)) => {
+                    policy.max_step = value.parse().map_err(|e| format!("max: {e}"))?;
+                }
+                Some(("trend", "any")) => policy.trend = Trend::Any,
+                Some(("trend", "increasing")) => policy.trend = Trend::Increasing,
+                Some(("trend", "decreasing")) => policy.trend = Trend::Decreasing,
+                None if setting == "plateaus" => policy.allow_plateaus = true,
+                _ => return Err(format!("Invalid policy setting: {setting}")),
+            }
+        }
+        if policy.min_step > policy.max_step {
+            return Err(format!(
+                "min step {} is greater than max step {}",
+                policy.min_step, policy.max_step
+            ));
+        }
+        Ok(policy)
+    }
+}
+
+impl Violation {
+    /// The index of the offending window, which is also the index of its first level
+    pub fn index(&self) -> usize {
+        match *self {
+            Violation::Plateau { index }
+            | Violation::StepTooSmall { index, .. }
+            | Violation::StepTooLarge { index, .. }
+            | Violation::DirectionChanged { index }
+            | Violation::WrongDirection { index } => index,
+        }
+    }
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::Plateau { index } => {
+                write!(f, "levels {index} and {} are equal", index + 1)
+            }
+            Violation::StepTooSmall { index, step } => {
+                write!(
+                    f,
+                    "step of {step} between levels {index} and {} is too small",
+                    index + 1
+                )
+            }
+            Violation::StepTooLarge { index, step } => {
+                write!(
+                    f,
+                    "step of {step} between levels {index} and {} is too large",
+                    index + 1
+                )
+            }
+            Violation::DirectionChanged { index } => {
+                write!(
+                    f,
+                    "direction changes between levels {index} and {}",
+                    index + 1
+                )
+            }
+            Violation::WrongDirection { index } => {
+                write!(
+                    f,
+                    "wrong direction between levels {index} and {}",
+                    index + 1
+                )
+            }
+        }
+    }
+}
+
+impl Display for Verdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Verdict::Safe => write!(f, "safe"),
+            Verdict::Dampened { violation, removed } => write!(
+                f,
+                "safe after removing levels {removed:?}, first offending window {}: {violation}",
+                violation.index()
+            ),
+            Verdict::Unsafe(violation) => write!(
+                f,
+                "unsafe, first offending window {}: {violation}",
+                violation.index()
+            ),
+        }
+    }
+}
+
 pub fn part_1(input: &str) -> usize {
-    solution(input, floor_is_safe)
+    solution(input, &SafetyPolicy::default(), 0)
 }
 
 pub fn part_2(input: &str) -> usize {
-    solution(input, |floor| {
-        floor_is_safe(floor) || floor_is_safe_less_one_level(floor)
-    })
+    solution(input, &SafetyPolicy::default(), 1)
 }
 
-/// Given a predicate for safety, count the number of safe floors
-fn solution(input: &str, is_safe: impl Fn(&[usize]) -> bool) -> usize {
+/// Count the number of safe floors when the problem dampener may remove up to
+/// `tolerance` levels from each floor
+pub fn solution(input: &str, policy: &SafetyPolicy, tolerance: usize) -> usize {
     input
         .lines()
         .map(parse_floor)
-        .filter(|floor| is_safe(floor))
+        .filter(|floor| levels_to_remove(floor, policy, tolerance).is_some())
         .count()
 }
 
-/// Check if the floor is safe
-fn floor_is_safe(floor: &[usize]) -> bool {
-    (all_decreasing(floor) || all_increasing(floor)) && greatest_difference(floor) <= 3
+/// Judge each floor of the input, giving the reason for any that are unsafe
+pub fn verdicts(input: &str, policy: &SafetyPolicy, tolerance: usize) -> Vec<Verdict> {
+    input
+        .lines()
+        .map(parse_floor)
+        .map(|floor| judge_floor(&floor, policy, tolerance))
+        .collect()
+}
+
+/// Describe the verdict for each floor of the input, one line per floor
+pub fn explain(input: &str, policy: &SafetyPolicy, tolerance: usize) -> String {
+    input
+        .lines()
+        .zip(verdicts(input, policy, tolerance))
+        .enumerate()
+        .fold(String::new(), |mut output, (line, (floor, verdict))| {
+            let _ = writeln!(output, "{}: {floor} => {verdict}", line + 1);
+            output
+        })
 }
 
-/// Check if the floor is safe with one level removed
-fn floor_is_safe_less_one_level(floor: &[usize]) -> bool {
-    for i in 0..floor.len() {
-        let mut new_floor = floor.to_vec();
-        new_floor.remove(i);
-        if floor_is_safe(&new_floor) {
-            return true;
-        }
+fn judge_floor(floor: &[usize], policy: &SafetyPolicy, tolerance: usize) -> Verdict {
+    let Some(violation) = policy.first_violation(floor) else {
+        return Verdict::Safe;
+    };
+    match levels_to_remove(floor, policy, tolerance) {
+        Some(removed) => Verdict::Dampened { violation, removed },
+        None => Verdict::Unsafe(violation),
     }
-    false
 }
 
-fn all_decreasing(floor: &[usize]) -> bool {
-    floor.windows(2).all(|w| w[0] > w[1])
+/// Find the fewest levels that must be removed for the floor to be safe, up to `tolerance`.
+/// Returns the indices of the removed levels, or None if the floor can't be made safe.
+pub fn levels_to_remove(
+    floor: &[usize],
+    policy: &SafetyPolicy,
+    tolerance: usize,
+) -> Option<Vec<usize>> {
+    policy
+        .trends()
+        .iter()
+        .filter_map(|&trend| {
+            levels_to_remove_directed(floor, tolerance, |a, b| policy.allows_step(a, b, trend))
+        })
+        .min_by_key(Vec::len)
 }
 
-fn all_increasing(floor: &[usize]) -> bool {
-    floor.windows(2).all(|w| w[0] < w[1])
-}
+/// Sweep the floor once, tracking for each level the fewest removals needed to make a
+/// safe prefix ending at that level. Since at most `tolerance` levels can be skipped, only
+/// the previous `tolerance + 1` levels need to be checked as the predecessor of each level.
+fn levels_to_remove_directed(
+    floor: &[usize],
+    tolerance: usize,
+    is_valid_step: impl Fn(usize, usize) -> bool,
+) -> Option<Vec<usize>> {
+    if floor.is_empty() {
+        return Some(vec![]);
+    }
+    let len = floor.len();
+    let mut removals: Vec<Option<usize>> = vec![None; len];
+    let mut previous: Vec<Option<usize>> = vec![None; len];
+    for i in 0..len {
+        // Every level before i could be removed to start the floor here
+        if i <= tolerance {
+            removals[i] = Some(i);
+        }
+        for j in i.saturating_sub(tolerance + 1)..i {
+            let Some(removed) = removals[j] else {
+                continue;
+            };
+            let removed = removed + i - j - 1;
+            if removed <= tolerance
+                && is_valid_step(floor[j], floor[i])
+                && removals[i].is_none_or(|best| removed < best)
+            {
+                removals[i] = Some(removed);
+                previous[i] = Some(j);
+            }
+        }
+    }
 
-fn greatest_difference(floor: &[usize]) -> usize {
-    floor.windows(2).map(|w| w[0].abs_diff(w[1])).max().unwrap()
+    // Every level after the last one kept is removed
+    let (_, last) = (len.saturating_sub(tolerance + 1)..len)
+        .filter_map(|i| removals[i].map(|removed| (removed + len - 1 - i, i)))
+        .filter(|&(removed, _)| removed <= tolerance)
+        .min()?;
+
+    // Walk back through the kept levels, everything else was removed
+    let mut kept = vec![false; len];
+    let mut current = Some(last);
+    while let Some(i) = current {
+        kept[i] = true;
+        current = previous[i];
+    }
+    Some((0..len).filter(|&i| !kept[i]).collect())
 }
 
 fn parse_floor(line: &str) -> Vec<usize> {
@@ -75,10 +360,148 @@ mod tests {
     #[bench]
     fn bench_solution(b: &mut Bencher) {
         let input = read_input(2);
-        b.iter(|| {
-            solution(&input, |floor| {
-                floor_is_safe(floor) || floor_is_safe_less_one_level(floor)
+        b.iter(|| solution(&input, &SafetyPolicy::default(), 1));
+    }
+
+    #[test]
+    fn test_levels_to_remove_safe() {
+        let policy = SafetyPolicy::default();
+        assert_eq!(levels_to_remove(&[7, 6, 4, 2, 1], &policy, 0), Some(vec![]));
+        assert_eq!(levels_to_remove(&[1, 3, 6, 7, 9], &policy, 1), Some(vec![]));
+    }
+
+    #[test]
+    fn test_levels_to_remove_one() {
+        let policy = SafetyPolicy::default();
+        assert_eq!(levels_to_remove(&[1, 3, 2, 4, 5], &policy, 0), None);
+        assert_eq!(
+            levels_to_remove(&[1, 3, 2, 4, 5], &policy, 1),
+            Some(vec![2])
+        );
+        assert_eq!(
+            levels_to_remove(&[8, 6, 4, 4, 1], &policy, 1),
+            Some(vec![3])
+        );
+        assert_eq!(levels_to_remove(&[9, 1, 2, 3], &policy, 1), Some(vec![0]));
+        assert_eq!(levels_to_remove(&[1, 2, 3, 9], &policy, 1), Some(vec![3]));
+    }
+
+    #[test]
+    fn test_levels_to_remove_unsafe() {
+        let policy = SafetyPolicy::default();
+        assert_eq!(levels_to_remove(&[1, 2, 7, 8, 9], &policy, 1), None);
+        assert_eq!(levels_to_remove(&[9, 7, 6, 2, 1], &policy, 1), None);
+    }
+
+    #[test]
+    fn test_levels_to_remove_many() {
+        let policy = SafetyPolicy::default();
+        assert_eq!(levels_to_remove(&[1, 5, 2, 3, 9, 4], &policy, 1), None);
+        assert_eq!(
+            levels_to_remove(&[1, 5, 2, 3, 9, 4], &policy, 2),
+            Some(vec![1, 4])
+        );
+        assert_eq!(
+            levels_to_remove(&[1, 9, 9, 9, 2], &policy, 3),
+            Some(vec![1, 2, 3])
+        );
+    }
+
+    #[test]
+    fn test_levels_to_remove_short() {
+        let policy = SafetyPolicy::default();
+        assert_eq!(levels_to_remove(&[], &policy, 0), Some(vec![]));
+        assert_eq!(levels_to_remove(&[5], &policy, 0), Some(vec![]));
+        assert_eq!(levels_to_remove(&[5, 5], &policy, 1), Some(vec![1]));
+    }
+
+    #[test]
+    fn test_levels_to_remove_policy() {
+        let policy: SafetyPolicy = "min=0,max=5,plateaus,trend=increasing".parse().unwrap();
+        assert_eq!(levels_to_remove(&[1, 1, 6, 6, 7], &policy, 0), Some(vec![]));
+        assert_eq!(levels_to_remove(&[7, 6, 4, 2, 1], &policy, 1), None);
+        assert_eq!(levels_to_remove(&[1, 2, 1, 3], &policy, 1), Some(vec![2]));
+    }
+
+    #[test]
+    fn test_parse_policy() {
+        assert_eq!("".parse::<SafetyPolicy>(), Ok(SafetyPolicy::default()));
+        assert_eq!(
+            "min=2,max=4,trend=decreasing".parse::<SafetyPolicy>(),
+            Ok(SafetyPolicy {
+                min_step: 2,
+                max_step: 4,
+                allow_plateaus: false,
+                trend: Trend::Decreasing,
             })
-        });
+        );
+        assert!("min=4,max=2".parse::<SafetyPolicy>().is_err());
+        assert!("sideways".parse::<SafetyPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_first_violation() {
+        let policy = SafetyPolicy::default();
+        assert_eq!(policy.first_violation(&[7, 6, 4, 2, 1]), None);
+        assert_eq!(
+            policy.first_violation(&[1, 2, 7, 8, 9]),
+            Some(Violation::StepTooLarge { index: 1, step: 5 })
+        );
+        assert_eq!(
+            policy.first_violation(&[1, 3, 2, 4, 5]),
+            Some(Violation::DirectionChanged { index: 1 })
+        );
+        assert_eq!(
+            policy.first_violation(&[8, 6, 4, 4, 1]),
+            Some(Violation::Plateau { index: 2 })
+        );
+        let policy = SafetyPolicy {
+            trend: Trend::Increasing,
+            min_step: 2,
+            ..SafetyPolicy::default()
+        };
+        assert_eq!(
+            policy.first_violation(&[9, 7, 6]),
+            Some(Violation::WrongDirection { index: 0 })
+        );
+        assert_eq!(
+            policy.first_violation(&[1, 3, 4]),
+            Some(Violation::StepTooSmall { index: 1, step: 1 })
+        );
+    }
+
+    #[test]
+    fn test_verdicts() {
+        let input = "7 6 4 2 1\n1 2 7 8 9\n1 3 2 4 5";
+        assert_eq!(verdicts(input, &SafetyPolicy::default(), 1), vec![
+            Verdict::Safe,
+            Verdict::Unsafe(Violation::StepTooLarge { index: 1, step: 5 }),
+            Verdict::Dampened {
+                violation: Violation::DirectionChanged { index: 1 },
+                removed: vec![2]
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_explain() {
+        let input = "7 6 4 2 1\n1 2 7 8 9\n1 3 2 4 5";
+        assert_eq!(
+            explain(input, &SafetyPolicy::default(), 0),
+            [
+                "1: 7 6 4 2 1 => safe",
+                "2: 1 2 7 8 9 => unsafe, first offending window 1: step of 5 between levels 1 and 2 is too large",
+                "3: 1 3 2 4 5 => unsafe, first offending window 1: direction changes between levels 1 and 2",
+                "",
+            ]
+            .join("\n")
+        );
+        assert!(
+            explain(input, &SafetyPolicy::default(), 1)
+                .lines()
+                .nth(2)
+                .unwrap()
+                .ends_with("=> safe after removing levels [2], first offending window 1: direction changes between levels 1 and 2")
+        );
     }
 }
@@ -1,89 +1,232 @@
-use nom::{
-    branch::alt,
-    bytes::complete::tag,
-    multi::many1,
-    sequence::{delimited, separated_pair},
-    IResult,
+use std::{
+    fmt::{Display, Write as _},
+    ops::RangeInclusive,
 };
-use regex::Regex;
 
-use crate::{parse_input, parse_number};
+/// The definition of an instruction the machine understands. Both the scanner and the
+/// interpreter are driven by these, so a new instruction only needs a new definition.
+#[derive(Debug)]
+pub struct InstructionDef {
+    pub name: &'static str,
+    pub arity: usize,
+    /// How many digits each operand may have
+    pub operand_width: RangeInclusive<usize>,
+    /// Run the instruction on the machine, returning whether it was executed
+    pub semantics: fn(&mut Machine, &[usize]) -> bool,
+}
+
+/// Multiply the operands and add them to the total if enabled
+pub const MUL: InstructionDef = InstructionDef {
+    name: "mul",
+    arity: 2,
+    operand_width: 1..=3,
+    semantics: |machine, operands| {
+        if machine.enabled {
+            machine.total += operands[0] * operands[1];
+        }
+        machine.enabled
+    },
+};
 
-type Program = Vec<Expr>;
+/// Enable the instructions that follow
+pub const DO: InstructionDef = InstructionDef {
+    name: "do",
+    arity: 0,
+    operand_width: 0..=0,
+    semantics: |machine, _| {
+        machine.enabled = true;
+        true
+    },
+};
 
-#[derive(Debug)]
-enum Expr {
-    Mul(usize, usize),
-    Do,
-    Dont,
+/// Disable the instructions that follow
+pub const DONT: InstructionDef = InstructionDef {
+    name: "don't",
+    arity: 0,
+    operand_width: 0..=0,
+    semantics: |machine, _| {
+        machine.enabled = false;
+        true
+    },
+};
+
+/// The instructions recognised in part 1
+pub const BASIC: &[InstructionDef] = &[MUL];
+
+/// The instructions recognised in part 2
+pub const CONDITIONAL: &[InstructionDef] = &[MUL, DO, DONT];
+
+/// An instruction recovered from the corrupted memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction<'a> {
+    pub def: &'a InstructionDef,
+    pub operands: Vec<usize>,
 }
 
-pub fn part_1(input: &str) -> usize {
-    let regex = Regex::new(r"mul\([0-9]{1,3},[0-9]{1,3}\)").unwrap();
-    solution(input, &regex)
+/// An instruction along with where it was found in the corrupted memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub instruction: Instruction<'a>,
+    /// Byte offset of the start of the instruction
+    pub offset: usize,
+    /// Length of the instruction in bytes
+    pub len: usize,
 }
 
-pub fn part_2(input: &str) -> usize {
-    let regex = Regex::new(r"don't\(\)|do\(\)|mul\([0-9]{1,3},[0-9]{1,3}\)").unwrap();
-    solution(input, &regex)
+/// The state of the machine running the program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Machine {
+    /// Whether instructions are currently enabled
+    pub enabled: bool,
+    /// The running result of the program
+    pub total: usize,
 }
 
-/// Parse the program from the corrupted input and interpret it.
-/// The regex is used to filter the corrupted input
-fn solution(input: &str, regex: &Regex) -> usize {
-    let input = scan_input(input, regex);
-    let program = parse_program(&input);
-    interpret(&program)
+/// The outcome of running a single instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step<'a> {
+    pub token: Token<'a>,
+    pub executed: bool,
+    /// The state of the machine after the instruction ran
+    pub machine: Machine,
 }
 
-/// Find all the mul(a,b) in the input where a and b are numbers at most 3 digits long
-fn scan_input(input: &str, regex: &Regex) -> String {
-    regex.find_iter(input).map(|m| m.as_str()).collect()
+impl PartialEq for InstructionDef {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
 }
 
-/// Parse mul(a,b) into a tuple of the two numbers to multiply
-fn mul_expr(input: &str) -> IResult<&str, Expr> {
-    let (rem, (a, b)) = delimited(
-        tag("mul("),
-        separated_pair(parse_number, tag(","), parse_number),
-        tag(")"),
-    )(input)?;
-    Ok((rem, Expr::Mul(a, b)))
+impl Eq for InstructionDef {}
+
+impl Display for Instruction<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let operands = self
+            .operands
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        write!(f, "{}({operands})", self.def.name)
+    }
 }
 
-/// Parse do() into a Do expression
-fn do_expr(input: &str) -> IResult<&str, Expr> {
-    let (rem, _) = tag("do()")(input)?;
-    Ok((rem, Expr::Do))
+impl Default for Machine {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            total: 0,
+        }
+    }
 }
 
-/// Parse don't() into a Dont expression
-fn dont_expr(input: &str) -> IResult<&str, Expr> {
-    let (rem, _) = tag("don't()")(input)?;
-    Ok((rem, Expr::Dont))
+pub fn part_1(input: &str) -> usize {
+    solution(input, BASIC)
 }
 
-/// Parse an expression from the input
-fn parse_expr(input: &str) -> IResult<&str, Expr> {
-    alt((do_expr, dont_expr, mul_expr))(input)
+pub fn part_2(input: &str) -> usize {
+    solution(input, CONDITIONAL)
 }
 
-/// Parse the program from the input
-fn parse_program(input: &str) -> Program {
-    parse_input(input, many1(parse_expr))
+/// Scan the corrupted input for the given instructions and interpret them
+pub fn solution(input: &str, instructions: &[InstructionDef]) -> usize {
+    interpret(lex(input, instructions))
+        .last()
+        .map_or(0, |step| step.machine.total)
 }
 
-/// Interpret the program.
-/// Do and Dont expressions toggle whether to execute the Mul expressions that follow
-fn interpret(program: &Program) -> usize {
-    program
+/// List every executed or skipped instruction with its offset in the corrupted input
+pub fn trace(input: &str, instructions: &[InstructionDef]) -> String {
+    interpret(lex(input, instructions)).fold(String::new(), |mut output, step| {
+        let status = if step.executed { "executed" } else { "skipped" };
+        let _ = writeln!(
+            output,
+            "{:>6}: {} {status}",
+            step.token.offset, step.token.instruction
+        );
+        output
+    })
+}
+
+/// Interpret the program, yielding each step as it runs
+fn interpret<'a>(tokens: impl Iterator<Item = Token<'a>>) -> impl Iterator<Item = Step<'a>> {
+    let mut machine = Machine::default();
+    tokens.map(move |token| {
+        let instruction = &token.instruction;
+        let executed = (instruction.def.semantics)(&mut machine, &instruction.operands);
+        Step {
+            token,
+            executed,
+            machine,
+        }
+    })
+}
+
+/// Scan the corrupted input once from left to right, yielding each valid instruction.
+/// Anything that doesn't form a complete instruction is skipped a byte at a time.
+fn lex<'a>(
+    input: &'a str,
+    instructions: &'a [InstructionDef],
+) -> impl Iterator<Item = Token<'a>> + 'a {
+    let bytes = input.as_bytes();
+    gen move {
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let lexed = instructions
+                .iter()
+                .find_map(|def| lex_instruction(def, &bytes[offset..]));
+            match lexed {
+                Some((instruction, len)) => {
+                    yield Token {
+                        instruction,
+                        offset,
+                        len,
+                    };
+                    offset += len;
+                }
+                None => offset += 1,
+            }
+        }
+    }
+}
+
+/// Try to read `name(a,b,...)` for the instruction from the start of the input,
+/// returning it and its length
+fn lex_instruction<'a>(def: &'a InstructionDef, input: &[u8]) -> Option<(Instruction<'a>, usize)> {
+    let mut rest = input
+        .strip_prefix(def.name.as_bytes())?
+        .strip_prefix(b"(")?;
+    let mut operands = Vec::with_capacity(def.arity);
+    for i in 0..def.arity {
+        if i > 0 {
+            rest = rest.strip_prefix(b",")?;
+        }
+        let (operand, len) = lex_number(rest, &def.operand_width)?;
+        operands.push(operand);
+        rest = &rest[len..];
+    }
+    rest = rest.strip_prefix(b")")?;
+    let instruction = Instruction { def, operands };
+    Some((instruction, input.len() - rest.len()))
+}
+
+/// Read a number with a digit count within `width`, returning it and its length. Numbers too
+/// large for a `usize` are not matched.
+fn lex_number(input: &[u8], width: &RangeInclusive<usize>) -> Option<(usize, usize)> {
+    let len = input
         .iter()
-        .fold((true, 0), |acc, expr| match expr {
-            Expr::Mul(a, b) => (acc.0, if acc.0 { acc.1 + a * b } else { acc.1 }),
-            Expr::Do => (true, acc.1),
-            Expr::Dont => (false, acc.1),
-        })
-        .1
+        .take(width.end() + 1)
+        .take_while(|byte| byte.is_ascii_digit())
+        .count();
+    if len == 0 || !width.contains(&len) {
+        return None;
+    }
+    let number = input[..len].iter().try_fold(0usize, |number, digit| {
+        number
+            .checked_mul(10)?
+            .checked_add(usize::from(digit - b'0'))
+    })?;
+    Some((number, len))
 }
 
 #[cfg(test)]
@@ -92,6 +235,9 @@ mod tests {
     use crate::read_input;
     use crate::test::Bencher;
 
+    const EXAMPLE: &str =
+        "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+
     #[test]
     fn test_part_1() {
         let input = read_input(3);
@@ -107,7 +253,88 @@ mod tests {
     #[bench]
     fn bench_solution(b: &mut Bencher) {
         let input = read_input(3);
-        let regex = Regex::new(r"don't\(\)|do\(\)|mul\([0-9]{1,3},[0-9]{1,3}\)").unwrap();
-        b.iter(|| solution(&input, &regex));
+        b.iter(|| solution(&input, CONDITIONAL));
+    }
+
+    #[test]
+    fn test_lex() {
+        let tokens: Vec<_> = lex(EXAMPLE, CONDITIONAL)
+            .map(|token| (token.instruction.to_string(), token.offset, token.len))
+            .collect();
+        assert_eq!(tokens, vec![
+            ("mul(2,4)".to_string(), 1, 8),
+            ("don't()".to_string(), 20, 7),
+            ("mul(5,5)".to_string(), 28, 8),
+            ("mul(11,8)".to_string(), 48, 9),
+            ("do()".to_string(), 59, 4),
+            ("mul(8,5)".to_string(), 64, 8),
+        ]);
+    }
+
+    #[test]
+    fn test_lex_operand_width() {
+        let input = " mul(1234,5) mul(123,4) mul(,1) mul(1,2 mul(1,2,3) ";
+        assert_eq!(lex(input, BASIC).count(), 1);
+        assert_eq!(lex_number(b"123)", &(1..=3)), Some((123, 3)));
+        assert_eq!(lex_number(b"1234)", &(1..=3)), None);
+        assert_eq!(lex_number(b"1)", &(2..=3)), None);
+        assert_eq!(lex_number(b")", &(1..=3)), None);
+        assert_eq!(
+            lex_number(b"18446744073709551615)", &(1..=30)),
+            Some((usize::MAX, 20))
+        );
+        assert_eq!(lex_number(b"18446744073709551616)", &(1..=30)), None);
+    }
+
+    #[test]
+    fn test_solution() {
+        assert_eq!(solution(EXAMPLE, BASIC), 161);
+        assert_eq!(solution(EXAMPLE, CONDITIONAL), 48);
+    }
+
+    #[test]
+    fn test_custom_instructions() {
+        const ADD: InstructionDef = InstructionDef {
+            name: "add",
+            arity: 2,
+            operand_width: 1..=5,
+            semantics: |machine, operands| {
+                if machine.enabled {
+                    machine.total += operands[0] + operands[1];
+                }
+                machine.enabled
+            },
+        };
+        const RESET: InstructionDef = InstructionDef {
+            name: "reset",
+            arity: 0,
+            operand_width: 0..=0,
+            semantics: |machine, _| {
+                *machine = Machine::default();
+                true
+            },
+        };
+        let instructions = &[MUL, ADD, RESET, DONT];
+        let input = "mul(2,3)reset()add(10000,2)xadd(1,2)don't()mul(4,4)add(1,1)";
+        assert_eq!(solution(input, instructions), 10005);
+        let input = "don't()mul(2,3)reset()add(1,2)";
+        assert_eq!(solution(input, instructions), 3);
+    }
+
+    #[test]
+    fn test_trace() {
+        assert_eq!(
+            trace(EXAMPLE, CONDITIONAL),
+            [
+                "     1: mul(2,4) executed",
+                "    20: don't() executed",
+                "    28: mul(5,5) skipped",
+                "    48: mul(11,8) skipped",
+                "    59: do() executed",
+                "    64: mul(8,5) executed",
+                "",
+            ]
+            .join("\n")
+        );
     }
 }
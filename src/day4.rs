@@ -1,98 +1,423 @@
-/// Scan every row column and diagonal for XMAS or SAMX
-pub fn part_1(input: &str) -> usize {
-    let rows: Vec<Vec<char>> = input.lines().map(|line| line.chars().collect()).collect();
-    let cols = transpose(&rows);
-    let diagonals_1 = diagonals(&rows);
-    let diagonals_2 = diagonals_rev(&rows);
-    rows.iter()
-        .chain(cols.iter())
-        .chain(diagonals_1.iter())
-        .chain(diagonals_2.iter())
-        .map(|row| find_all_xmas(row))
-        .sum()
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Write as _,
+    str::FromStr,
+};
+
+use crate::{Grid, Window, windows_2d};
+
+/// The word searched for in part 1
+const XMAS: &str = "XMAS";
+
+/// The shape searched for in part 2
+const X_MAS: &str = "M.S\n.A.\nM.S";
+
+/// A direction to read a word in, clockwise from north
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
 }
 
-/// Slide a 2d window over the input and count the number of MAS crosses
-pub fn part_2(input: &str) -> usize {
-    let rows: Vec<Vec<char>> = input.lines().map(|line| line.chars().collect()).collect();
-    windows_2d(&rows, 3)
-        .into_iter()
-        .filter(|window| window_is_xmas(window))
-        .count()
-}
-
-/// Transpose the input grid of characters
-fn transpose<T: Copy>(input: &[Vec<T>]) -> Vec<Vec<T>> {
-    let mut result = vec![Vec::with_capacity(input.len()); input[0].len()];
-    for row in input {
-        for (i, &cell) in row.iter().enumerate() {
-            result[i].push(cell);
+/// A 2D arrangement of letters where `None` matches any letter
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Pattern {
+    cells: Vec<Vec<Option<char>>>,
+}
+
+/// An Aho-Corasick automaton for finding many words in a single pass over a line
+#[derive(Debug)]
+struct Automaton {
+    /// The trie of words, mapping each state and letter to the next state
+    transitions: Vec<HashMap<char, usize>>,
+    /// The state for the longest proper suffix of each state that is also in the trie
+    fail: Vec<usize>,
+    /// The number of words ending at each state, including through its fail links
+    outputs: Vec<usize>,
+}
+
+/// A word found in the grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match<'a> {
+    pub word: &'a str,
+    pub row: usize,
+    pub col: usize,
+    pub direction: Direction,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    /// The ANSI foreground colour code used when rendering words in this direction
+    fn ansi_colour(self) -> u8 {
+        match self {
+            Direction::North => 31,
+            Direction::NorthEast => 32,
+            Direction::East => 33,
+            Direction::SouthEast => 34,
+            Direction::South => 35,
+            Direction::SouthWest => 36,
+            Direction::West => 91,
+            Direction::NorthWest => 92,
+        }
+    }
+
+    /// The (row, col) step taken to move one cell in this direction
+    fn delta(self) -> (isize, isize) {
+        match self {
+            Direction::North => (-1, 0),
+            Direction::NorthEast => (-1, 1),
+            Direction::East => (0, 1),
+            Direction::SouthEast => (1, 1),
+            Direction::South => (1, 0),
+            Direction::SouthWest => (1, -1),
+            Direction::West => (0, -1),
+            Direction::NorthWest => (-1, -1),
         }
     }
-    result
 }
 
-/// Get the diagonals of the input grid from NE to SW
-fn diagonals(input: &[Vec<char>]) -> Vec<Vec<char>> {
-    let mut diagonals = vec![vec![]; input.len() + input[0].len() - 1];
-    for (r, row) in input.iter().enumerate() {
-        for (c, col) in row.iter().enumerate() {
-            diagonals[r + c].push(*col);
+impl Pattern {
+    fn height(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn width(&self) -> usize {
+        self.cells[0].len()
+    }
+
+    /// Rotate the pattern a quarter turn clockwise
+    fn rotate(&self) -> Self {
+        let cells = (0..self.width())
+            .map(|col| self.cells.iter().rev().map(|row| row[col]).collect())
+            .collect();
+        Self { cells }
+    }
+
+    /// Mirror the pattern left to right
+    fn reflect(&self) -> Self {
+        let cells = self
+            .cells
+            .iter()
+            .map(|row| row.iter().rev().copied().collect())
+            .collect();
+        Self { cells }
+    }
+
+    /// Every distinct rotation and reflection of the pattern
+    pub fn orientations(&self) -> Vec<Pattern> {
+        let mut orientations: Vec<Pattern> = Vec::with_capacity(8);
+        let mut pattern = self.clone();
+        for _ in 0..4 {
+            for candidate in [pattern.reflect(), pattern.clone()] {
+                if !orientations.contains(&candidate) {
+                    orientations.push(candidate);
+                }
+            }
+            pattern = pattern.rotate();
         }
+        orientations
+    }
+
+    /// Check if the window of the grid, which must be the same size as the pattern, matches
+    fn matches(&self, window: &Window<char>) -> bool {
+        self.cells
+            .iter()
+            .zip(window.rows())
+            .all(|(pattern_row, row)| {
+                pattern_row
+                    .iter()
+                    .zip(row.iter())
+                    .all(|(cell, letter)| cell.is_none_or(|cell| cell == *letter))
+            })
+    }
+
+    /// Find the top left cell of every match of the pattern in any orientation. Matches of
+    /// different orientations can share a top left cell, so it is listed once for each.
+    pub fn find_all(&self, grid: &[Vec<char>]) -> Vec<(usize, usize)> {
+        let mut positions: Vec<(usize, usize)> = self
+            .orientations()
+            .iter()
+            .flat_map(|pattern| pattern.find_all_oriented(grid))
+            .collect();
+        positions.sort_unstable();
+        positions
+    }
+
+    /// Mark every non-wildcard cell of every match of the pattern in any orientation.
+    /// Patterns have no direction so the cells are left uncoloured.
+    pub fn highlights(&self, grid: &[Vec<char>]) -> HashMap<(usize, usize), Option<Direction>> {
+        let mut highlights = HashMap::new();
+        for pattern in self.orientations() {
+            for (row, col) in pattern.find_all_oriented(grid) {
+                for (r, pattern_row) in pattern.cells.iter().enumerate() {
+                    for (c, cell) in pattern_row.iter().enumerate() {
+                        if cell.is_some() {
+                            highlights.insert((row + r, col + c), None);
+                        }
+                    }
+                }
+            }
+        }
+        highlights
+    }
+
+    /// Find the top left cell of every match of the pattern as it is
+    fn find_all_oriented(&self, grid: &[Vec<char>]) -> Vec<(usize, usize)> {
+        windows_2d(grid, self.height(), self.width(), 1, 1)
+            .filter(|window| self.matches(window))
+            .map(|window| (window.row, window.col))
+            .collect()
     }
-    diagonals
 }
 
-/// Get the diagonals of the input grid from NW to SE
-fn diagonals_rev(input: &[Vec<char>]) -> Vec<Vec<char>> {
-    let mut diagonals = vec![vec![]; input.len() + input[0].len() - 1];
-    for (r, row) in input.iter().rev().enumerate() {
-        for (c, col) in row.iter().enumerate() {
-            diagonals[r + c].push(*col);
+/// Parse a pattern from lines of letters where `.` matches any letter
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let cells: Vec<Vec<Option<char>>> = s
+            .lines()
+            .map(|line| {
+                line.chars()
+                    .map(|c| if c == '.' { None } else { Some(c) })
+                    .collect()
+            })
+            .collect();
+        if cells.is_empty() || cells[0].is_empty() {
+            return Err("Pattern is empty".to_string());
+        }
+        if cells.iter().any(|row| row.len() != cells[0].len()) {
+            return Err("Pattern rows must all be the same length".to_string());
         }
+        Ok(Self { cells })
     }
-    diagonals
 }
 
-/// Find all XMAS or SAMX in the input
-fn find_all_xmas(chars: &[char]) -> usize {
-    chars
-        .windows(4)
-        .filter(|w| w == &['X', 'M', 'A', 'S'] || w == &['S', 'A', 'M', 'X'])
-        .count()
+impl Automaton {
+    fn new<'a>(words: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut automaton = Self {
+            transitions: vec![HashMap::new()],
+            fail: vec![0],
+            outputs: vec![0],
+        };
+        for word in words {
+            let mut state = 0;
+            for letter in word.chars() {
+                state = match automaton.transitions[state].get(&letter) {
+                    Some(&next) => next,
+                    None => {
+                        automaton.transitions.push(HashMap::new());
+                        automaton.fail.push(0);
+                        automaton.outputs.push(0);
+                        let next = automaton.transitions.len() - 1;
+                        automaton.transitions[state].insert(letter, next);
+                        next
+                    }
+                };
+            }
+            automaton.outputs[state] += 1;
+        }
+        automaton.build_fail_links();
+        automaton
+    }
+
+    /// Breadth first over the trie so that every shorter state is linked before it is needed
+    fn build_fail_links(&mut self) {
+        let mut queue: VecDeque<usize> = self.transitions[0].values().copied().collect();
+        while let Some(state) = queue.pop_front() {
+            let children: Vec<(char, usize)> = self.transitions[state]
+                .iter()
+                .map(|(&letter, &child)| (letter, child))
+                .collect();
+            for (letter, child) in children {
+                let fail = self.next_state(self.fail[state], letter);
+                self.fail[child] = fail;
+                self.outputs[child] += self.outputs[fail];
+                queue.push_back(child);
+            }
+        }
+    }
+
+    fn next_state(&self, mut state: usize, letter: char) -> usize {
+        loop {
+            if let Some(&next) = self.transitions[state].get(&letter) {
+                return next;
+            }
+            if state == 0 {
+                return 0;
+            }
+            state = self.fail[state];
+        }
+    }
+
+    /// Count every occurrence of every word in the line, including overlapping ones
+    fn count_matches<'a>(&self, line: impl Iterator<Item = &'a char>) -> usize {
+        line.scan(0, |state, &letter| {
+            *state = self.next_state(*state, letter);
+            Some(self.outputs[*state])
+        })
+        .sum()
+    }
 }
 
-/// Check if the 2d window contains a MAS cross
-fn window_is_xmas(chars: &[&[char]]) -> bool {
-    // A should be in the middle
-    if chars[1][1] != 'A' {
-        return false;
+/// Search for XMAS written in any direction
+pub fn part_1(input: &str) -> usize {
+    let grid = parse_grid(input);
+    word_search(&grid, &[XMAS], &Direction::ALL).len()
+}
+
+/// Search for two MAS in the shape of an X, in any orientation
+pub fn part_2(input: &str) -> usize {
+    let grid = parse_grid(input);
+    let pattern: Pattern = X_MAS.parse().unwrap();
+    pattern.find_all(&grid).len()
+}
+
+/// Render the grid with only the letters of the matches for the part, like in the puzzle
+pub fn render_matches(input: &str, part: u8, colour: bool) -> String {
+    let grid = parse_grid(input);
+    let highlights = match part {
+        1 => highlight_words(&word_search(&grid, &[XMAS], &Direction::ALL)),
+        2 => X_MAS.parse::<Pattern>().unwrap().highlights(&grid),
+        _ => panic!("Invalid part: {part}"),
+    };
+    render(&grid, &highlights, colour)
+}
+
+/// Render the grid replacing every letter that isn't highlighted with `.`.
+/// If `colour` is set, highlighted letters are coloured by the direction of their word.
+pub fn render(
+    grid: &[Vec<char>],
+    highlights: &HashMap<(usize, usize), Option<Direction>>,
+    colour: bool,
+) -> String {
+    let mut output = String::new();
+    for (row, line) in grid.iter().enumerate() {
+        for (col, &letter) in line.iter().enumerate() {
+            match highlights.get(&(row, col)) {
+                None => output.push('.'),
+                Some(Some(direction)) if colour => {
+                    let _ = write!(output, "\x1b[{}m{letter}\x1b[0m", direction.ansi_colour());
+                }
+                Some(_) => output.push(letter),
+            }
+        }
+        output.push('\n');
     }
+    output
+}
 
-    // M's at top
-    chars[0][0] == 'M' && chars[0][2] == 'M' && chars[2][0] == 'S' && chars[2][2] == 'S' ||
-        // M's at bottom
-        chars[0][0] == 'S' && chars[0][2] == 'S' && chars[2][0] == 'M' && chars[2][2] == 'M' ||
-        // M's at left
-        chars[0][0] == 'M' && chars[0][2] == 'S' && chars[2][0] == 'M' && chars[2][2] == 'S' ||
-        // M's at right
-        chars[0][0] == 'S' && chars[0][2] == 'M' && chars[2][0] == 'S' && chars[2][2] == 'M'
+/// Map each cell covered by a word to the direction of the word.
+/// Where words cross, the direction of the last one wins.
+pub fn highlight_words(matches: &[Match]) -> HashMap<(usize, usize), Option<Direction>> {
+    let mut highlights = HashMap::new();
+    for found in matches {
+        let (row_step, col_step) = found.direction.delta();
+        // Matches always lie inside the grid so the cells never wrap
+        for i in 0..found.word.chars().count() as isize {
+            let row = found.row.wrapping_add_signed(row_step * i);
+            let col = found.col.wrapping_add_signed(col_step * i);
+            highlights.insert((row, col), Some(found.direction));
+        }
+    }
+    highlights
 }
 
-/// Slide a 2d window over the input
-fn windows_2d(input: &[Vec<char>], size: usize) -> Vec<Vec<&[char]>> {
-    let mut windows = Vec::new();
-    for r in 0..=input.len() - size {
-        for c in 0..=input[0].len() - size {
-            windows.push(
-                input[r..r + size]
-                    .iter()
-                    .map(|row| &row[c..c + size])
-                    .collect(),
-            );
+/// Find every occurrence of each word reading from a cell in each of the directions
+pub fn word_search<'a>(
+    grid: &[Vec<char>],
+    words: &[&'a str],
+    directions: &[Direction],
+) -> Vec<Match<'a>> {
+    let words: Vec<(&str, Vec<char>)> = words
+        .iter()
+        .map(|&word| (word, word.chars().collect()))
+        .collect();
+    let mut matches = Vec::new();
+    for (row, line) in grid.iter().enumerate() {
+        for col in 0..line.len() {
+            for (word, chars) in &words {
+                for &direction in directions {
+                    if word_at(grid, chars, row, col, direction) {
+                        matches.push(Match {
+                            word,
+                            row,
+                            col,
+                            direction,
+                        });
+                    }
+                }
+            }
         }
     }
-    windows
+    matches
+}
+
+/// Check if the word can be read from the cell in the direction
+fn word_at(
+    grid: &[Vec<char>],
+    word: &[char],
+    row: usize,
+    col: usize,
+    direction: Direction,
+) -> bool {
+    let (row_step, col_step) = direction.delta();
+    word.iter().enumerate().all(|(i, &letter)| {
+        let cell_row = row.checked_add_signed(row_step * i as isize);
+        let cell_col = col.checked_add_signed(col_step * i as isize);
+        cell_row
+            .zip(cell_col)
+            .and_then(|(r, c)| grid.get(r)?.get(c))
+            .is_some_and(|&cell| cell == letter)
+    })
+}
+
+/// Count every occurrence of each word in any of the eight directions in one pass over each
+/// row, column and diagonal. The lines are read in place, and reading them backwards is
+/// covered by also searching for each word reversed.
+pub fn dictionary_search(grid: &[Vec<char>], words: &[&str]) -> usize {
+    let reversed: Vec<String> = words
+        .iter()
+        .map(|word| word.chars().rev().collect::<String>())
+        .collect();
+    let automaton = Automaton::new(
+        words
+            .iter()
+            .copied()
+            .chain(reversed.iter().map(String::as_str)),
+    );
+    let grid = Grid::new(grid);
+    let rows: usize = grid.rows().map(|line| automaton.count_matches(line)).sum();
+    let cols: usize = grid.cols().map(|line| automaton.count_matches(line)).sum();
+    let diagonals: usize = grid
+        .diagonals()
+        .map(|line| automaton.count_matches(line))
+        .sum();
+    let diagonals_rev: usize = grid
+        .diagonals_rev()
+        .map(|line| automaton.count_matches(line))
+        .sum();
+    rows + cols + diagonals + diagonals_rev
+}
+
+fn parse_grid(input: &str) -> Vec<Vec<char>> {
+    input.lines().map(|line| line.chars().collect()).collect()
 }
 
 #[cfg(test)]
@@ -125,80 +450,207 @@ mod tests {
         b.iter(|| part_2(&input));
     }
 
+    const EXAMPLE: &str = "MMMSXXMASM
+MSAMXMSMSA
+AMXSXMAAMM
+MSAMASMSMX
+XMASAMXAMM
+XXAMMXXAMA
+SMSMSASXSS
+SAXAMASAAA
+MAMMMXMMMM
+MXMXAXMASX";
+
+    #[bench]
+    fn bench_dictionary_search(b: &mut Bencher) {
+        let input = read_input(4);
+        let grid = parse_grid(&input);
+        b.iter(|| dictionary_search(&grid, &["XMAS"]));
+    }
+
     #[test]
-    fn test_transpose() {
-        let input = vec![vec!['a', 'b', 'c'], vec!['d', 'e', 'f'], vec![
-            'g', 'h', 'i',
-        ]];
-        let expected = vec![vec!['a', 'd', 'g'], vec!['b', 'e', 'h'], vec![
-            'c', 'f', 'i',
-        ]];
-        assert_eq!(transpose(&input), expected);
+    fn test_automaton_count_matches() {
+        let automaton = Automaton::new(["he", "she", "his", "hers"]);
+        let line: Vec<char> = "ushers".chars().collect();
+        assert_eq!(automaton.count_matches(line.iter()), 3);
+        let automaton = Automaton::new(["aa", "a"]);
+        let line: Vec<char> = "aaaa".chars().collect();
+        assert_eq!(automaton.count_matches(line.iter()), 7);
     }
 
     #[test]
-    fn test_diagonals_symmetric() {
-        let input = vec![vec!['a', 'b', 'c'], vec!['d', 'e', 'f'], vec![
-            'g', 'h', 'i',
-        ]];
-        let expected = vec![
-            vec!['a'],
-            vec!['b', 'd'],
-            vec!['c', 'e', 'g'],
-            vec!['f', 'h'],
-            vec!['i'],
-        ];
-        assert_eq!(diagonals(&input), expected);
+    fn test_dictionary_search() {
+        let grid = parse_grid(EXAMPLE);
+        assert_eq!(dictionary_search(&grid, &["XMAS"]), 18);
+        assert_eq!(
+            dictionary_search(&grid, &["XMAS", "MAS", "AM"]),
+            word_search(&grid, &["XMAS", "MAS", "AM"], &Direction::ALL).len()
+        );
+        // Palindromes and words listed with their reverse are found in both reading directions
+        for words in [&["XMAS", "SAMX"][..], &["ABA"], &["MAM", "XMAS"]] {
+            assert_eq!(
+                dictionary_search(&grid, words),
+                word_search(&grid, words, &Direction::ALL).len(),
+                "{words:?}"
+            );
+        }
+        let grid = parse_grid("ABA\nB.B\nABA");
+        assert_eq!(dictionary_search(&grid, &["ABA"]), 8);
+        assert_eq!(
+            dictionary_search(&grid, &["ABA"]),
+            word_search(&grid, &["ABA"], &Direction::ALL).len()
+        );
     }
 
     #[test]
-    fn test_diagonals_asymmetric_1() {
-        let input = vec![vec!['a', 'b', 'c', 'd', 'e', 'f'], vec![
-            'g', 'h', 'i', 'j', 'k', 'l',
-        ]];
-        let expected = vec![
-            vec!['a'],
-            vec!['b', 'g'],
-            vec!['c', 'h'],
-            vec!['d', 'i'],
-            vec!['e', 'j'],
-            vec!['f', 'k'],
-            vec!['l'],
+    fn test_dictionary_search_rectangular() {
+        let grids = [
+            "X...\n.M..\n..A.\n...S\n....",
+            "MSX\nMMX\nXXA\nMAM",
+            "XMASAMX\nMMAAMMS\nSAXMASX",
+            "S..\n.A.\n..M\n..X\n.M.\nA..\nS..",
         ];
-        assert_eq!(diagonals(&input), expected);
+        let dictionaries: [&[&str]; 3] = [&["XMAS"], &["MAM", "A", "AS"], &["XMAS", "SAMX", "MM"]];
+        for grid in grids.map(parse_grid) {
+            for words in dictionaries {
+                assert_eq!(
+                    dictionary_search(&grid, words),
+                    word_search(&grid, words, &Direction::ALL).len(),
+                    "{grid:?} {words:?}"
+                );
+            }
+        }
+        let grid = parse_grid(grids[0]);
+        assert_eq!(dictionary_search(&grid, &["XMAS"]), 1);
     }
 
     #[test]
-    fn test_diagonals_asymmetric_2() {
-        let input = vec![
-            vec!['a', 'b'],
-            vec!['c', 'd'],
-            vec!['e', 'f'],
-            vec!['g', 'h'],
-            vec!['i', 'j'],
-        ];
-        let expected = vec![
-            vec!['a'],
-            vec!['b', 'c'],
-            vec!['d', 'e'],
-            vec!['f', 'g'],
-            vec!['h', 'i'],
-            vec!['j'],
-        ];
-        assert_eq!(diagonals(&input), expected);
+    fn test_word_search_example() {
+        let grid = parse_grid(EXAMPLE);
+        assert_eq!(word_search(&grid, &["XMAS"], &Direction::ALL).len(), 18);
+        assert_eq!(word_search(&grid, &["XMAS"], &[Direction::East]).len(), 3);
+        assert_eq!(
+            word_search(&grid, &["XMAS", "SAMX"], &[Direction::East]).len(),
+            5
+        );
     }
 
     #[test]
-    fn test_windows_2d() {
-        let input = vec![vec!['a', 'b', 'c'], vec!['d', 'e', 'f'], vec![
-            'g', 'h', 'i',
-        ]];
-        let expected = vec![
-            vec![vec!['a', 'b'], vec!['d', 'e']],
-            vec![vec!['b', 'c'], vec!['e', 'f']],
-            vec![vec!['d', 'e'], vec!['g', 'h']],
-            vec![vec!['e', 'f'], vec!['h', 'i']],
-        ];
-        assert_eq!(windows_2d(&input, 2), expected);
+    fn test_word_search_matches() {
+        let grid = parse_grid("XMAS\nMM..\nA.A.\nS..S");
+        let matches = word_search(&grid, &["XMAS", "SAMX"], &Direction::ALL);
+        assert_eq!(matches, vec![
+            Match {
+                word: "XMAS",
+                row: 0,
+                col: 0,
+                direction: Direction::East
+            },
+            Match {
+                word: "XMAS",
+                row: 0,
+                col: 0,
+                direction: Direction::SouthEast
+            },
+            Match {
+                word: "XMAS",
+                row: 0,
+                col: 0,
+                direction: Direction::South
+            },
+            Match {
+                word: "SAMX",
+                row: 0,
+                col: 3,
+                direction: Direction::West
+            },
+            Match {
+                word: "SAMX",
+                row: 3,
+                col: 0,
+                direction: Direction::North
+            },
+            Match {
+                word: "SAMX",
+                row: 3,
+                col: 3,
+                direction: Direction::NorthWest
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_pattern_orientations() {
+        let pattern: Pattern = X_MAS.parse().unwrap();
+        let orientations: Vec<String> = pattern
+            .orientations()
+            .iter()
+            .map(|pattern| {
+                pattern
+                    .cells
+                    .iter()
+                    .map(|row| row.iter().map(|cell| cell.unwrap_or('.')).collect())
+                    .collect::<Vec<String>>()
+                    .join("/")
+            })
+            .collect();
+        assert_eq!(orientations, vec![
+            "S.M/.A./S.M",
+            "M.S/.A./M.S",
+            "M.M/.A./S.S",
+            "S.S/.A./M.M"
+        ]);
+
+        let pattern: Pattern = "AB\nC.".parse().unwrap();
+        assert_eq!(pattern.orientations().len(), 8);
+        let pattern: Pattern = "XMAS".parse().unwrap();
+        assert_eq!(pattern.orientations().len(), 4);
+    }
+
+    #[test]
+    fn test_pattern_find_all() {
+        let grid = parse_grid(EXAMPLE);
+        let pattern: Pattern = X_MAS.parse().unwrap();
+        assert_eq!(pattern.find_all(&grid).len(), 9);
+
+        let grid = parse_grid("XMAS.\n.SAMX\n.....");
+        let pattern: Pattern = "XMAS".parse().unwrap();
+        assert_eq!(pattern.find_all(&grid), vec![(0, 0), (1, 1)]);
+        let pattern: Pattern = "X\nM\nA\nS".parse().unwrap();
+        assert_eq!(pattern.find_all(&grid), vec![(0, 0), (1, 1)]);
+
+        let grid = parse_grid("AA\nAA");
+        let pattern: Pattern = "AA\nA.".parse().unwrap();
+        assert_eq!(pattern.find_all(&grid), vec![(0, 0); 4]);
+    }
+
+    #[test]
+    fn test_render_words() {
+        let grid = parse_grid("..X...\n.SAMX.\n.A..A.\nXMAS.S\n.X....");
+        let highlights = highlight_words(&word_search(&grid, &[XMAS], &Direction::ALL));
+        assert_eq!(
+            render(&grid, &highlights, false),
+            "..X...\n.SAMX.\n.A..A.\nXMAS.S\n.X....\n"
+        );
+        let grid = parse_grid("XMAS\nABCD");
+        let highlights = highlight_words(&word_search(&grid, &[XMAS], &Direction::ALL));
+        assert_eq!(render(&grid, &highlights, false), "XMAS\n....\n");
+        assert_eq!(
+            render(&grid, &highlights, true),
+            "\x1b[33mX\x1b[0m\x1b[33mM\x1b[0m\x1b[33mA\x1b[0m\x1b[33mS\x1b[0m\n....\n"
+        );
+    }
+
+    #[test]
+    fn test_render_pattern() {
+        let grid = parse_grid("MXS.\nXAXX\nMXSX");
+        let highlights = X_MAS.parse::<Pattern>().unwrap().highlights(&grid);
+        assert_eq!(render(&grid, &highlights, true), "M.S.\n.A..\nM.S.\n");
+    }
+
+    #[test]
+    fn test_parse_pattern() {
+        assert!("".parse::<Pattern>().is_err());
+        assert!("AB\nC".parse::<Pattern>().is_err());
     }
 }
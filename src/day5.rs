@@ -1,6 +1,7 @@
 use std::{
-    collections::{HashMap, HashSet},
-    fmt::Debug,
+    cmp::Ordering,
+    collections::HashMap,
+    fmt::{Debug, Display, Write as _},
 };
 
 use nom::{character::complete, multi::separated_list1, sequence::separated_pair};
@@ -10,77 +11,386 @@ use crate::{parse_input, parse_number};
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 struct Rule(usize, usize);
 
+/// Pages are all two digit numbers, so the rules between them fit in a dense table
+const MAX_PAGE: usize = 100;
+
+/// For each page, a bitset of the pages that must come after it
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RuleMatrix {
+    after: [u128; MAX_PAGE],
+}
+
+/// The rules for which page must come before another, used as a comparator
+struct RuleOrder {
+    rules: RuleMatrix,
+}
+
+/// Why the pages of an update can't be put in a single order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrderError {
+    /// The rules form a cycle, listed in order with the first page repeated at the end
+    Cycle(Vec<usize>),
+    /// No rule decides which of these two pages comes first
+    Ambiguous(usize, usize),
+}
+
+/// A rule broken by an update: `before` must come before `after` but doesn't
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Violation {
+    pub before: usize,
+    pub before_position: usize,
+    pub after: usize,
+    pub after_position: usize,
+}
+
+/// Move a page from one position of an update to another, shifting the pages in between
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub page: usize,
+    pub from: usize,
+    pub to: usize,
+}
+
+/// Everything wrong with an update and how to fix it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    pub violations: Vec<Violation>,
+    /// The fewest moves that put the update in order, if it has a single order
+    pub moves: Result<Vec<Move>, OrderError>,
+}
+
+impl RuleMatrix {
+    /// Check if a rule says `before` must come before `after`
+    fn must_precede(&self, before: usize, after: usize) -> bool {
+        self.after[before] & (1 << after) != 0
+    }
+}
+
+impl RuleOrder {
+    fn new<'a>(rules: impl Iterator<Item = &'a Rule>) -> Self {
+        Self {
+            rules: compress_rules(rules),
+        }
+    }
+
+    /// Compare two pages by the rules. None if no rule, or contradicting rules, relate them.
+    fn compare(&self, a: usize, b: usize) -> Option<Ordering> {
+        if a == b {
+            return Some(Ordering::Equal);
+        }
+        match (self.rules.must_precede(a, b), self.rules.must_precede(b, a)) {
+            (true, false) => Some(Ordering::Less),
+            (false, true) => Some(Ordering::Greater),
+            _ => None,
+        }
+    }
+
+    /// Sort the update by the rules, first checking that they are a strict total order over
+    /// its pages. The standard sort would give an arbitrary order, or panic, if they weren't.
+    fn sort(&self, update: &mut [usize]) -> Result<(), OrderError> {
+        self.validate(update)?;
+        update.sort_by(|&a, &b| self.compare(a, b).expect("the update was validated"));
+        Ok(())
+    }
+
+    /// Every pair of pages must be ordered by exactly one rule, so the rules over the update
+    /// form a tournament. A tournament has no cycles exactly when each page comes before a
+    /// different number of the other pages.
+    fn validate(&self, update: &[usize]) -> Result<(), OrderError> {
+        let mut precedes = vec![0; update.len()];
+        for (i, &a) in update.iter().enumerate() {
+            for (j, &b) in update.iter().enumerate().skip(i + 1) {
+                match (self.rules.must_precede(a, b), self.rules.must_precede(b, a)) {
+                    (true, false) => precedes[i] += 1,
+                    (false, true) => precedes[j] += 1,
+                    (true, true) => {
+                        return Err(OrderError::Cycle(vec![a.min(b), a.max(b), a.min(b)]));
+                    }
+                    (false, false) => return Err(OrderError::Ambiguous(a, b)),
+                }
+            }
+        }
+        let mut seen = vec![false; update.len()];
+        for count in precedes {
+            if seen[count] {
+                return Err(topological_sort(update, &self.rules)
+                    .expect_err("a tournament with a repeated score has a cycle"));
+            }
+            seen[count] = true;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}|{} broken: {} at position {} is after {} at position {}",
+            self.before,
+            self.after,
+            self.before,
+            self.before_position,
+            self.after,
+            self.after_position
+        )
+    }
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "move {} from position {} to {}",
+            self.page, self.from, self.to
+        )
+    }
+}
+
+impl Display for OrderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderError::Cycle(pages) => {
+                let pages: Vec<String> = pages.iter().map(ToString::to_string).collect();
+                write!(f, "the rules form a cycle {}", pages.join("|"))
+            }
+            OrderError::Ambiguous(a, b) => {
+                write!(f, "no rule orders {a} and {b}")
+            }
+        }
+    }
+}
+
 pub fn part_1(input: &str) -> usize {
     let mut lines = input.lines();
     let rules = parse_rules(&mut lines);
     let pages = parse_pages(lines);
-    let rule_set: HashSet<&Rule> = rules.iter().collect();
+    let rules = compress_rules(rules.iter());
     pages
         .iter()
-        .filter(|page| page_is_ordered(&rule_set, page))
+        .filter(|page| page_is_ordered(&rules, page))
         .map(|page| page[page.len() / 2])
         .sum()
 }
 
-/// To correct the unordered pages, sort the page numbers by the number of rules
-/// that apply to the number. The intuition being that the first number must not
-/// appear on the left side of any rules that apply to the page, else it wouldn't
-/// be first because some other number must then come before. The second number must only
-/// have one rule apply because only one number is in front of it and so on.
+/// To correct the unordered pages, sort them using the rules as the comparator. This is only
+/// sound if the rules give a strict total order over the pages of the update, otherwise the
+/// middle page isn't well defined, so that is checked first.
 pub fn part_2(input: &str) -> usize {
     let mut lines = input.lines();
     let rules = parse_rules(&mut lines);
     let pages = parse_pages(lines);
-    let rule_set: HashSet<&Rule> = rules.iter().collect();
+    let order = RuleOrder::new(rules.iter());
 
     pages
         .into_iter()
-        .filter(|page| !page_is_ordered(&rule_set, page))
+        .filter(|page| !page_is_ordered(&order.rules, page))
         .map(|mut page| {
-            let filtered_rules = rules
-                .iter()
-                .filter(|rule| page.contains(&rule.0) && page.contains(&rule.1));
-            let compressed_rules = compress_rules(filtered_rules);
-            let rule_counts = rules_applied_per_number(&page, &compressed_rules);
-            page.sort_by_key(|&page| rule_counts[&page]);
+            order
+                .sort(&mut page)
+                .unwrap_or_else(|e| panic!("Can't order update {page:?}: {e}"));
             page[page.len() / 2]
         })
         .sum()
 }
 
-fn page_is_ordered(rule_set: &HashSet<&Rule>, page: &[usize]) -> bool {
+/// Describe each update that is out of order, listing the rules it breaks and the moves
+/// needed to fix it
+pub fn explain(input: &str) -> String {
+    let mut lines = input.lines();
+    let rules = parse_rules(&mut lines);
+    let pages = parse_pages(lines);
+    let compressed_rules = compress_rules(rules.iter());
+
+    let mut output = String::new();
+    for page in &pages {
+        let update: Vec<String> = page.iter().map(ToString::to_string).collect();
+        let update = update.join(",");
+        if page_is_ordered(&compressed_rules, page) {
+            let _ = writeln!(output, "{update}: ordered");
+            continue;
+        }
+        let _ = writeln!(output, "{update}: out of order");
+        let explanation = explain_update(page, &compressed_rules);
+        for violation in &explanation.violations {
+            let _ = writeln!(output, "  {violation}");
+        }
+        match explanation.moves {
+            Ok(moves) => {
+                for step in moves {
+                    let _ = writeln!(output, "  {step}");
+                }
+            }
+            Err(e) => {
+                let _ = writeln!(output, "  can't be ordered: {e}");
+            }
+        }
+    }
+    output
+}
+
+/// Find every pair of pages in the update that breaks a rule, and the fewest moves to fix it
+fn explain_update(update: &[usize], rules: &RuleMatrix) -> Explanation {
+    let mut violations = Vec::new();
+    for (after_position, &after) in update.iter().enumerate() {
+        for (before_position, &before) in update.iter().enumerate().skip(after_position + 1) {
+            if rules.must_precede(before, after) {
+                violations.push(Violation {
+                    before,
+                    before_position,
+                    after,
+                    after_position,
+                });
+            }
+        }
+    }
+    let moves = topological_sort(update, rules).map(|sorted| minimal_moves(update, &sorted));
+    Explanation { violations, moves }
+}
+
+/// The pages in the longest subsequence of the update that is already in sorted order can
+/// stay where they are. Every other page has to move once, which is the fewest moves possible.
+/// Each is moved in sorted order to sit just before the first settled page that follows it.
+fn minimal_moves(update: &[usize], sorted: &[usize]) -> Vec<Move> {
+    let rank: HashMap<usize, usize> = sorted.iter().enumerate().map(|(i, &p)| (p, i)).collect();
+    let ranks: Vec<usize> = update.iter().map(|page| rank[page]).collect();
+
+    // Longest increasing subsequence of ranks, tracking each element's predecessor
+    let mut length = vec![1; ranks.len()];
+    let mut previous = vec![None; ranks.len()];
+    for i in 0..ranks.len() {
+        for j in 0..i {
+            if ranks[j] < ranks[i] && length[j] + 1 > length[i] {
+                length[i] = length[j] + 1;
+                previous[i] = Some(j);
+            }
+        }
+    }
+    let mut settled = vec![false; sorted.len()];
+    let mut current = (0..ranks.len()).max_by_key(|&i| (length[i], std::cmp::Reverse(i)));
+    while let Some(i) = current {
+        settled[ranks[i]] = true;
+        current = previous[i];
+    }
+
+    let mut order = ranks;
+    let mut moves = Vec::new();
+    for rank in 0..sorted.len() {
+        if settled[rank] {
+            continue;
+        }
+        let from = order.iter().position(|&r| r == rank).unwrap();
+        order.remove(from);
+        let to = order
+            .iter()
+            .position(|&r| r > rank && settled[r])
+            .unwrap_or(order.len());
+        order.insert(to, rank);
+        settled[rank] = true;
+        if from != to {
+            moves.push(Move {
+                page: sorted[rank],
+                from,
+                to,
+            });
+        }
+    }
+    moves
+}
+
+fn page_is_ordered(rules: &RuleMatrix, page: &[usize]) -> bool {
     page.windows(2)
-        .all(|window| rule_set.contains(&Rule(window[0], window[1])))
+        .all(|window| rules.must_precede(window[0], window[1]))
 }
 
 /// For each number, get the set of numbers that must come after it
-fn compress_rules<'a>(
-    rules: impl Iterator<Item = &'a Rule>,
-) -> HashMap<&'a usize, HashSet<&'a usize>> {
-    rules.fold(HashMap::new(), |mut map, Rule(left, right)| {
-        let entry = map.entry(left).or_default();
-        entry.insert(right);
-        map
-    })
-}
-
-/// For each number in the `page`, check how many rules apply to it.
-/// This could also be thought of as how many numbers must come before it.
-fn rules_applied_per_number(
-    page: &[usize],
-    rules: &HashMap<&usize, HashSet<&usize>>,
-) -> HashMap<usize, usize> {
-    let rule_sets = rules.values().collect::<Vec<_>>();
-    page.iter()
-        .fold(HashMap::<usize, usize>::new(), |mut map, &number| {
-            let applicable_rule_count = rule_sets
+fn compress_rules<'a>(rules: impl Iterator<Item = &'a Rule>) -> RuleMatrix {
+    rules.fold(
+        RuleMatrix {
+            after: [0; MAX_PAGE],
+        },
+        |mut matrix, &Rule(left, right)| {
+            assert!(
+                left < MAX_PAGE && right < MAX_PAGE,
+                "Pages must be less than {MAX_PAGE}: {left}|{right}"
+            );
+            matrix.after[left] |= 1 << right;
+            matrix
+        },
+    )
+}
+
+/// Order the pages of the update with Kahn's algorithm, using only the rules between pages
+/// in the update. The order is unique only if there is never more than one page ready to
+/// be placed next.
+fn topological_sort(update: &[usize], rules: &RuleMatrix) -> Result<Vec<usize>, OrderError> {
+    // Count the rules into each page of the update, indexed by position
+    let mut in_degree: Vec<usize> = update
+        .iter()
+        .map(|&page| {
+            update
                 .iter()
-                .filter(|rule| rule.contains(&number))
-                .count();
-            let entry = map.entry(number).or_insert(0);
-            *entry += applicable_rule_count;
-            map
+                .filter(|&&before| rules.must_precede(before, page))
+                .count()
         })
+        .collect();
+
+    let mut ready: Vec<usize> = (0..update.len()).filter(|&i| in_degree[i] == 0).collect();
+    let mut sorted = Vec::with_capacity(update.len());
+    while let Some(i) = ready.pop() {
+        if let Some(&other) = ready.last() {
+            return Err(OrderError::Ambiguous(update[other], update[i]));
+        }
+        sorted.push(update[i]);
+        for j in 0..update.len() {
+            if rules.must_precede(update[i], update[j]) {
+                in_degree[j] -= 1;
+                if in_degree[j] == 0 {
+                    ready.push(j);
+                }
+            }
+        }
+    }
+
+    if sorted.len() < update.len() {
+        let remaining: Vec<usize> = (0..update.len())
+            .filter(|&i| in_degree[i] > 0)
+            .map(|i| update[i])
+            .collect();
+        return Err(OrderError::Cycle(find_cycle(&remaining, rules)));
+    }
+    Ok(sorted)
+}
+
+/// Find a cycle among the pages left over by Kahn's algorithm. Every one of them still has a
+/// rule from another leftover page, so walking those rules backwards must revisit a page.
+fn find_cycle(remaining: &[usize], rules: &RuleMatrix) -> Vec<usize> {
+    let mut current = *remaining
+        .iter()
+        .min()
+        .expect("a cycle needs at least one page");
+    let mut path = vec![current];
+    loop {
+        current = *remaining
+            .iter()
+            .filter(|&&page| rules.must_precede(page, current))
+            .min()
+            .expect("every remaining page has a rule from another remaining page");
+        if let Some(start) = path.iter().position(|&page| page == current) {
+            let mut cycle: Vec<usize> = path[start..].iter().rev().copied().collect();
+            // Start from the lowest page so the same cycle is always reported the same way
+            let lowest = cycle
+                .iter()
+                .enumerate()
+                .min_by_key(|&(_, page)| page)
+                .unwrap()
+                .0;
+            cycle.rotate_left(lowest);
+            cycle.push(cycle[0]);
+            return cycle;
+        }
+        path.push(current);
+    }
 }
 
 fn parse_rules<'a>(input: &mut impl Iterator<Item = &'a str>) -> Vec<Rule> {
@@ -98,7 +408,15 @@ fn parse_rules<'a>(input: &mut impl Iterator<Item = &'a str>) -> Vec<Rule> {
 
 fn parse_pages<'a>(input: impl Iterator<Item = &'a str>) -> Vec<Vec<usize>> {
     input
-        .map(|line| parse_input(line, separated_list1(complete::char(','), parse_number)))
+        .map(|line| {
+            let pages: Vec<usize> =
+                parse_input(line, separated_list1(complete::char(','), parse_number));
+            assert!(
+                pages.iter().all(|&page| page < MAX_PAGE),
+                "Pages must be less than {MAX_PAGE}: {line}"
+            );
+            pages
+        })
         .collect()
 }
 
@@ -106,6 +424,7 @@ fn parse_pages<'a>(input: impl Iterator<Item = &'a str>) -> Vec<Vec<usize>> {
 mod tests {
     use super::*;
     use crate::read_input;
+    use std::collections::HashSet;
     use test::Bencher;
 
     #[test]
@@ -157,24 +476,202 @@ mod tests {
             Rule(3, 4),
         ];
         let compressed = compress_rules(rules.iter());
+        let mut expected = [0; MAX_PAGE];
+        expected[1] = 0b11100;
+        expected[2] = 0b11000;
+        expected[3] = 0b10000;
+        assert_eq!(compressed, RuleMatrix { after: expected });
+        assert!(compressed.must_precede(1, 4));
+        assert!(!compressed.must_precede(4, 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Pages must be less than 100")]
+    fn test_compress_rules_large_page() {
+        compress_rules([Rule(1, 100)].iter());
+    }
+
+    #[test]
+    #[should_panic(expected = "Pages must be less than 100: 1,128")]
+    fn test_parse_pages_large_page() {
+        parse_pages(["1,128"].into_iter());
+    }
+
+    /// Look up the rule for every pair of pages in every update, as sorting does
+    fn lookup_all<T>(
+        pages: &[Vec<usize>],
+        rules: &T,
+        lookup: impl Fn(&T, usize, usize) -> bool,
+    ) -> usize {
+        pages
+            .iter()
+            .map(|page| {
+                page.iter()
+                    .flat_map(|&a| page.iter().map(move |&b| (a, b)))
+                    .filter(|&(a, b)| lookup(rules, a, b))
+                    .count()
+            })
+            .sum()
+    }
+
+    #[bench]
+    fn bench_rule_lookup_hash_set(b: &mut Bencher) {
+        let input = read_input(5);
+        let mut lines = input.lines();
+        let rules = parse_rules(&mut lines);
+        let pages = parse_pages(lines);
+        let rule_set: HashSet<&Rule> = rules.iter().collect();
+        b.iter(|| {
+            lookup_all(&pages, &rule_set, |rule_set, a, b| {
+                rule_set.contains(&Rule(a, b))
+            })
+        });
+    }
+
+    #[bench]
+    fn bench_rule_lookup_matrix(b: &mut Bencher) {
+        let input = read_input(5);
+        let mut lines = input.lines();
+        let rules = parse_rules(&mut lines);
+        let pages = parse_pages(lines);
+        let matrix = compress_rules(rules.iter());
+        b.iter(|| lookup_all(&pages, &matrix, RuleMatrix::must_precede));
+    }
+
+    #[test]
+    fn test_topological_sort() {
+        let rules = [Rule(1, 2), Rule(2, 3), Rule(1, 3), Rule(3, 4), Rule(5, 1)];
+        let compressed = compress_rules(rules.iter());
+        assert_eq!(topological_sort(&[3, 1, 2], &compressed), Ok(vec![1, 2, 3]));
         assert_eq!(
-            compressed,
-            HashMap::from([
-                (&1, HashSet::from([&2, &3, &4])),
-                (&2, HashSet::from([&3, &4])),
-                (&3, HashSet::from([&4]))
-            ])
+            topological_sort(&[4, 3, 2, 1, 5], &compressed),
+            Ok(vec![5, 1, 2, 3, 4])
         );
     }
 
     #[test]
-    fn test_rules_applied_per_number() {
-        let rules = HashMap::from([
-            (&1, HashSet::from([&2, &3, &4])),
-            (&2, HashSet::from([&3, &4])),
-            (&3, HashSet::from([&4])),
-        ]);
-        let rule_counts = rules_applied_per_number(&[1, 2, 3, 4], &rules);
-        assert_eq!(rule_counts, HashMap::from([(1, 0), (2, 1), (3, 2), (4, 3)]));
+    fn test_topological_sort_ambiguous() {
+        let rules = [Rule(1, 2), Rule(1, 3)];
+        let compressed = compress_rules(rules.iter());
+        assert_eq!(
+            topological_sort(&[1, 2, 3], &compressed),
+            Err(OrderError::Ambiguous(2, 3))
+        );
+        // Rules for pages outside the update don't help
+        let rules = [Rule(1, 2), Rule(2, 3)];
+        let compressed = compress_rules(rules.iter());
+        assert_eq!(
+            topological_sort(&[1, 3], &compressed),
+            Err(OrderError::Ambiguous(1, 3))
+        );
+    }
+
+    #[test]
+    fn test_topological_sort_cycle() {
+        let rules = [Rule(1, 2), Rule(2, 3), Rule(3, 1), Rule(3, 4), Rule(0, 1)];
+        let compressed = compress_rules(rules.iter());
+        let error = topological_sort(&[0, 1, 2, 3, 4], &compressed).unwrap_err();
+        assert_eq!(error, OrderError::Cycle(vec![1, 2, 3, 1]));
+        assert_eq!(error.to_string(), "the rules form a cycle 1|2|3|1");
+    }
+
+    #[test]
+    fn test_explain_update() {
+        let rules = [
+            Rule(97, 75),
+            Rule(97, 47),
+            Rule(75, 47),
+            Rule(97, 13),
+            Rule(47, 13),
+        ];
+        let compressed = compress_rules(rules.iter());
+        let explanation = explain_update(&[75, 97, 47, 13], &compressed);
+        assert_eq!(explanation.violations, vec![Violation {
+            before: 97,
+            before_position: 1,
+            after: 75,
+            after_position: 0,
+        }]);
+        assert_eq!(
+            explanation.moves,
+            Ok(vec![Move {
+                page: 97,
+                from: 1,
+                to: 0
+            }])
+        );
+        assert_eq!(
+            explanation.violations[0].to_string(),
+            "97|75 broken: 97 at position 1 is after 75 at position 0"
+        );
+    }
+
+    #[test]
+    fn test_minimal_moves() {
+        assert_eq!(minimal_moves(&[1, 2, 3], &[1, 2, 3]), vec![]);
+        // Moving 1 to the front beats moving 2 and 3 back
+        assert_eq!(minimal_moves(&[2, 3, 4, 1], &[1, 2, 3, 4]), vec![Move {
+            page: 1,
+            from: 3,
+            to: 0
+        }]);
+        let moves = minimal_moves(&[5, 4, 3, 2, 1], &[1, 2, 3, 4, 5]);
+        assert_eq!(moves.len(), 4);
+        let mut update = vec![5, 4, 3, 2, 1];
+        for Move { page, from, to } in moves {
+            assert_eq!(update.remove(from), page);
+            update.insert(to, page);
+        }
+        assert_eq!(update, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_explain() {
+        let input = "1|2\n2|3\n1|3\n\n1,2,3\n3,1,2\n2,1";
+        assert_eq!(
+            explain(input),
+            [
+                "1,2,3: ordered",
+                "3,1,2: out of order",
+                "  1|3 broken: 1 at position 1 is after 3 at position 0",
+                "  2|3 broken: 2 at position 2 is after 3 at position 0",
+                "  move 3 from position 0 to 2",
+                "2,1: out of order",
+                "  1|2 broken: 1 at position 1 is after 2 at position 0",
+                "  move 1 from position 1 to 0",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_rule_order_sort() {
+        let rules = [Rule(1, 2), Rule(2, 3), Rule(1, 3), Rule(4, 1)];
+        let order = RuleOrder::new(rules.iter());
+        assert_eq!(order.compare(1, 2), Some(Ordering::Less));
+        assert_eq!(order.compare(3, 1), Some(Ordering::Greater));
+        assert_eq!(order.compare(4, 2), None);
+        let mut update = vec![3, 1, 2];
+        assert_eq!(order.sort(&mut update), Ok(()));
+        assert_eq!(update, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_rule_order_invalid() {
+        let rules = [Rule(1, 2), Rule(2, 3), Rule(3, 1), Rule(4, 5), Rule(5, 4)];
+        let order = RuleOrder::new(rules.iter());
+        let mut update = vec![2, 3, 1];
+        assert_eq!(
+            order.sort(&mut update),
+            Err(OrderError::Cycle(vec![1, 2, 3, 1]))
+        );
+        // The update is left as it was
+        assert_eq!(update, vec![2, 3, 1]);
+        assert_eq!(
+            order.sort(&mut [5, 4]),
+            Err(OrderError::Cycle(vec![4, 5, 4]))
+        );
+        assert_eq!(order.sort(&mut [1, 2, 4]), Err(OrderError::Ambiguous(1, 4)));
     }
 }
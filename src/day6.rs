@@ -1,15 +1,22 @@
 use std::{
-    collections::HashSet,
-    fmt::{Debug, Display},
+    collections::BTreeSet,
+    fmt::{Debug, Display, Write as _},
+    str::FromStr,
 };
 
+use rayon::prelude::*;
+
 type Map = Vec<Vec<Tile>>;
 
+/// A cell on the guard's path and the guard just before it first enters it
+type Arrival = ((usize, usize), Guard);
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum Tile {
     Empty,
     Obstruction,
-    Exit,
+    /// Can only be entered moving in the direction, and blocks the guard otherwise
+    OneWay(Direction),
 }
 
 impl Display for Tile {
@@ -17,13 +24,16 @@ impl Display for Tile {
         write!(f, "{}", match self {
             Tile::Empty => '.',
             Tile::Obstruction => '#',
-            Tile::Exit => 'E',
+            Tile::OneWay(Direction::Up) => 'U',
+            Tile::OneWay(Direction::Down) => 'D',
+            Tile::OneWay(Direction::Left) => 'L',
+            Tile::OneWay(Direction::Right) => 'R',
         })
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum Direction {
+pub enum Direction {
     Up,
     Right,
     Down,
@@ -41,6 +51,82 @@ impl Display for Direction {
     }
 }
 
+impl Direction {
+    fn turn_right(self) -> Self {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+}
+
+/// Which way the guard turns when it is blocked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Turn {
+    Right,
+    Left,
+    Reverse,
+}
+
+impl Turn {
+    fn apply(self, direction: Direction) -> Direction {
+        match self {
+            Turn::Right => direction.turn_right(),
+            Turn::Left => direction.turn_right().turn_right().turn_right(),
+            Turn::Reverse => direction.turn_right().turn_right(),
+        }
+    }
+}
+
+/// The rules the guards move by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MovementPolicy {
+    pub turn: Turn,
+    /// Leaving the map on one edge re-enters it on the opposite edge
+    pub wrap: bool,
+    /// One-way tiles block guards entering them from the wrong direction,
+    /// otherwise they are walked over like empty tiles
+    pub one_way: bool,
+    /// Every guard on the map patrols, otherwise the map must have a single guard
+    pub multiple_guards: bool,
+}
+
+impl Default for MovementPolicy {
+    /// The rules from the puzzle: a single guard turning right and leaving at the edges
+    fn default() -> Self {
+        Self {
+            turn: Turn::Right,
+            wrap: false,
+            one_way: false,
+            multiple_guards: false,
+        }
+    }
+}
+
+/// Parse a policy such as `turn=left,wrap,one-way,guards`.
+/// Anything not given keeps the value from the default policy.
+impl FromStr for MovementPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut policy = Self::default();
+        for setting in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match setting.split_once('=') {
+                Some(("turn", "right")) => policy.turn = Turn::Right,
+                Some(("turn", "left")) => policy.turn = Turn::Left,
+                Some(("turn", "reverse")) => policy.turn = Turn::Reverse,
+                None if setting == "wrap" => policy.wrap = true,
+                None if setting == "one-way" => policy.one_way = true,
+                None if setting == "guards" => policy.multiple_guards = true,
+                _ => return Err(format!("Invalid policy setting: {setting}")),
+            }
+        }
+        Ok(policy)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Guard {
     row: usize,
@@ -49,82 +135,545 @@ struct Guard {
     exited: bool,
 }
 
+/// A cell of the input map, counted from zero at the top left
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub row: usize,
+    pub col: usize,
+}
+
+impl FromStr for Position {
+    type Err = String;
+
+    /// Parse a position written as `row,col`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (row, col) = s
+            .split_once(',')
+            .ok_or_else(|| format!("Expected `row,col`, got `{s}`"))?;
+        let parse = |value: &str| {
+            value
+                .trim()
+                .parse()
+                .map_err(|e| format!("Invalid coordinate `{value}`: {e}"))
+        };
+        Ok(Self {
+            row: parse(row)?,
+            col: parse(col)?,
+        })
+    }
+}
+
+/// Why the guard can't patrol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatrolError {
+    /// Every way the guard can turn is blocked
+    BoxedIn(Position),
+    /// The guard walks in a loop and never leaves the map
+    Loops,
+}
+
+impl Display for PatrolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatrolError::BoxedIn(Position { row, col }) => {
+                write!(f, "The guard at row {row}, col {col} is boxed in")
+            }
+            PatrolError::Loops => write!(f, "The guard never leaves the map"),
+        }
+    }
+}
+
+/// Where the guard is and which way it faces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GuardState {
+    pub position: Position,
+    pub direction: Direction,
+}
+
+/// An obstruction that traps the guard in a loop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Loop {
+    pub obstruction: Position,
+    /// The number of steps the guard takes to go around the loop once
+    pub length: usize,
+}
+
+/// Everything about the guard's patrol, for plotting
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patrol {
+    /// Every state of the guard from the start until it leaves the map
+    pub states: Vec<GuardState>,
+    /// The cells where the guard turned
+    pub turns: BTreeSet<Position>,
+    /// Every obstruction that makes the guard loop, in the order the guard reaches them
+    pub loops: Vec<Loop>,
+}
+
+impl From<Guard> for GuardState {
+    fn from(guard: Guard) -> Self {
+        Self {
+            position: Position {
+                row: guard.row,
+                col: guard.col,
+            },
+            direction: guard.direction,
+        }
+    }
+}
+
+impl Patrol {
+    /// Export the patrol as a JSON object with `states`, `turns` and `loops` arrays
+    pub fn to_json(&self) -> String {
+        let position = |Position { row, col }: Position| format!(r#"{{"row":{row},"col":{col}}}"#);
+        let states = self.states.iter().map(|state| {
+            format!(
+                r#"{{"row":{},"col":{},"direction":"{}"}}"#,
+                state.position.row, state.position.col, state.direction
+            )
+        });
+        let turns = self.turns.iter().map(|&turn| position(turn));
+        let loops = self.loops.iter().map(|cycle| {
+            format!(
+                r#"{{"obstruction":{},"length":{}}}"#,
+                position(cycle.obstruction),
+                cycle.length
+            )
+        });
+        let mut json = String::new();
+        let _ = write!(
+            json,
+            r#"{{"states":[{}],"turns":[{}],"loops":[{}]}}"#,
+            states.collect::<Vec<_>>().join(","),
+            turns.collect::<Vec<_>>().join(","),
+            loops.collect::<Vec<_>>().join(",")
+        );
+        json
+    }
+}
+
+/// Trail marks for a cell the guard walked through vertically or horizontally
+const VERTICAL: u8 = 1;
+const HORIZONTAL: u8 = 2;
+
+/// For every cell and direction, the position along the row or column of the nearest
+/// obstruction, or `None` if the guard would walk off the map. This lets the guard jump
+/// straight to its next turn instead of walking there one cell at a time.
+struct JumpTable {
+    nearest: Vec<Vec<[Option<usize>; 4]>>,
+}
+
+impl JumpTable {
+    fn new(map: &Map) -> Self {
+        let (rows, cols) = (map.len(), map[0].len());
+        let blocked = |row: usize, col: usize| map[row][col] == Tile::Obstruction;
+        let mut nearest = vec![vec![[None; 4]; cols]; rows];
+        for row in 1..rows {
+            for col in 0..cols {
+                nearest[row][col][Direction::Up as usize] = if blocked(row - 1, col) {
+                    Some(row - 1)
+                } else {
+                    nearest[row - 1][col][Direction::Up as usize]
+                };
+            }
+        }
+        for row in (0..rows - 1).rev() {
+            for col in 0..cols {
+                nearest[row][col][Direction::Down as usize] = if blocked(row + 1, col) {
+                    Some(row + 1)
+                } else {
+                    nearest[row + 1][col][Direction::Down as usize]
+                };
+            }
+        }
+        for (row, nearest) in nearest.iter_mut().enumerate() {
+            for col in 1..cols {
+                nearest[col][Direction::Left as usize] = if blocked(row, col - 1) {
+                    Some(col - 1)
+                } else {
+                    nearest[col - 1][Direction::Left as usize]
+                };
+            }
+            for col in (0..cols - 1).rev() {
+                nearest[col][Direction::Right as usize] = if blocked(row, col + 1) {
+                    Some(col + 1)
+                } else {
+                    nearest[col + 1][Direction::Right as usize]
+                };
+            }
+        }
+        Self { nearest }
+    }
+}
+
+/// The directions the guard has faced on each cell, one bit per direction
+struct VisitedStates {
+    cols: usize,
+    directions: Vec<u8>,
+}
+
+impl VisitedStates {
+    fn new(map: &Map) -> Self {
+        Self {
+            cols: map[0].len(),
+            directions: vec![0; map.len() * map[0].len()],
+        }
+    }
+
+    /// Mark the guard's state as visited, returning whether it is new
+    fn insert(&mut self, guard: Guard) -> bool {
+        let cell = &mut self.directions[guard.row * self.cols + guard.col];
+        let bit = 1 << guard.direction as u8;
+        let new = *cell & bit == 0;
+        *cell |= bit;
+        new
+    }
+}
+
 pub fn part_1(input: &str) -> usize {
-    let (map, guard) = parse_map(input);
-    let visited = tiles_visited(&map, guard);
-    visited.len()
+    solution(input, &MovementPolicy::default()).unwrap_or_else(|e| panic!("{e}"))
 }
 
 // Place tiles and see if the guard returns to the same position with same direction.
 // Reduce search space by only placing tiles on the original path of the guard.
-// TODO: Try to more efficiently move the guard by taking many steps at once
+// The path up to the obstruction is unchanged, so each check starts from the guard just
+// before it first walks into the obstruction's cell.
+// The guard jumps from turn to turn, so only the turns need to be checked for a loop.
 pub fn part_2(input: &str) -> usize {
-    let (mut map, starting_guard) = parse_map(input);
-    let visited = tiles_visited(&map, starting_guard);
+    let (map, starting_guard) = parse_map(input);
+    let jumps = JumpTable::new(&map);
+    let arrivals = first_arrivals(&map, starting_guard).unwrap_or_else(|e| panic!("{e}"));
 
-    let mut loop_positions = 0;
-    for (row, col) in visited {
-        let mut current_guard = starting_guard;
-        let mut guards = HashSet::from([current_guard]);
-        map[row][col] = Tile::Obstruction;
-        loop {
-            let new_guard = move_guard(&map, current_guard);
-            if new_guard.exited {
-                break;
-            }
-            if guards.contains(&new_guard) {
-                // The guard looped
-                loop_positions += 1;
-                break;
-            }
-            guards.insert(new_guard);
-            current_guard = new_guard;
+    arrivals
+        .par_iter()
+        .filter(|&&(obstruction, guard)| guard_loops(&map, &jumps, guard, obstruction))
+        .count()
+}
+
+/// Check if the guard walks in a loop once the obstruction is added to the map
+fn guard_loops(map: &Map, jumps: &JumpTable, guard: Guard, obstruction: (usize, usize)) -> bool {
+    let mut current_guard = guard;
+    let mut turns = VisitedStates::new(map);
+    loop {
+        current_guard = jump_guard(jumps, current_guard, obstruction);
+        if current_guard.exited {
+            return false;
+        }
+        if !turns.insert(current_guard) {
+            return true;
         }
-        map[row][col] = Tile::Empty;
     }
-    loop_positions
 }
 
-fn tiles_visited(map: &Vec<Vec<Tile>>, guard: Guard) -> HashSet<(usize, usize)> {
+/// Follow the guard with the obstruction added to the map, returning the number of steps
+/// around the loop it gets stuck in, if any
+fn loop_length(
+    map: &Map,
+    jumps: &JumpTable,
+    guard: Guard,
+    obstruction: (usize, usize),
+) -> Option<usize> {
     let mut current_guard = guard;
-    let mut visited = HashSet::from([(current_guard.row, current_guard.col)]);
+    let mut visited = VisitedStates::new(map);
+    let mut turns = Vec::new();
+    loop {
+        current_guard = jump_guard(jumps, current_guard, obstruction);
+        if current_guard.exited {
+            return None;
+        }
+        if !visited.insert(current_guard) {
+            break;
+        }
+        turns.push(current_guard);
+    }
+    let start = turns
+        .iter()
+        .position(|&turn| turn == current_guard)
+        .unwrap();
+    let cycle = &turns[start..];
+    let length = cycle
+        .iter()
+        .zip(cycle.iter().cycle().skip(1))
+        .map(|(from, to)| from.row.abs_diff(to.row) + from.col.abs_diff(to.col))
+        .sum();
+    Some(length)
+}
+
+/// Record the guard's patrol, the cells it turns on and every obstruction that makes it loop
+pub fn patrol(input: &str) -> Result<Patrol, PatrolError> {
+    let (map, starting_guard) = parse_map(input);
+    let mut states = vec![GuardState::from(starting_guard)];
+    let mut turns = BTreeSet::new();
+    let mut visited = VisitedStates::new(&map);
+    visited.insert(starting_guard);
+    let mut current_guard = starting_guard;
     loop {
-        let new_guard = move_guard(map, current_guard);
+        let new_guard = move_guard(&map, current_guard, &MovementPolicy::default())?;
+        if new_guard.direction != current_guard.direction {
+            turns.insert(GuardState::from(current_guard).position);
+        }
         if new_guard.exited {
             break;
         }
-        visited.insert((new_guard.row, new_guard.col));
+        if !visited.insert(new_guard) {
+            return Err(PatrolError::Loops);
+        }
+        states.push(new_guard.into());
         current_guard = new_guard;
     }
-    visited
-}
 
-/// Move the guard according to the direction and the map.
-fn move_guard(map: &Map, guard: Guard) -> Guard {
-    let (next_row, next_col, next_direction) = match guard.direction {
-        Direction::Up => (guard.row - 1, guard.col, Direction::Right),
-        Direction::Down => (guard.row + 1, guard.col, Direction::Left),
-        Direction::Left => (guard.row, guard.col - 1, Direction::Up),
-        Direction::Right => (guard.row, guard.col + 1, Direction::Down),
-    };
+    let jumps = JumpTable::new(&map);
+    let loops = first_arrivals(&map, starting_guard)?
+        .par_iter()
+        .filter_map(|&((row, col), guard)| {
+            let length = loop_length(&map, &jumps, guard, (row, col))?;
+            Some(Loop {
+                obstruction: Position { row, col },
+                length,
+            })
+        })
+        .collect();
+    Ok(Patrol {
+        states,
+        turns,
+        loops,
+    })
+}
 
-    match map[next_row][next_col] {
-        Tile::Empty => Guard {
-            row: next_row,
-            col: next_col,
-            ..guard
-        },
-        Tile::Obstruction => {
-            let guard = Guard {
-                direction: next_direction,
-                ..guard
+/// Walk the guard out of the map and list each cell it enters, apart from the starting cell,
+/// in the order it first arrives there. Each cell is paired with the guard on the previous
+/// cell facing it, which is where the guard would first meet an obstruction placed there.
+fn first_arrivals(map: &Map, guard: Guard) -> Result<Vec<Arrival>, PatrolError> {
+    let mut seen = vec![vec![false; map[0].len()]; map.len()];
+    seen[guard.row][guard.col] = true;
+    let mut visited = VisitedStates::new(map);
+    visited.insert(guard);
+    let mut arrivals = Vec::new();
+    let mut current_guard = guard;
+    loop {
+        let new_guard = move_guard(map, current_guard, &MovementPolicy::default())?;
+        if new_guard.exited {
+            break;
+        }
+        if !visited.insert(new_guard) {
+            return Err(PatrolError::Loops);
+        }
+        if !seen[new_guard.row][new_guard.col] {
+            seen[new_guard.row][new_guard.col] = true;
+            let before = Guard {
+                direction: new_guard.direction,
+                ..current_guard
             };
-            move_guard(map, guard)
+            arrivals.push(((new_guard.row, new_guard.col), before));
+        }
+        current_guard = new_guard;
+    }
+    Ok(arrivals)
+}
+
+/// Jump the guard to the cell before the next obstruction and turn right, or out of the map.
+/// The extra `obstruction` isn't in the jump table so it is checked separately.
+fn jump_guard(jumps: &JumpTable, guard: Guard, obstruction: (usize, usize)) -> Guard {
+    let nearest = jumps.nearest[guard.row][guard.col][guard.direction as usize];
+    let (obstruction_row, obstruction_col) = obstruction;
+    let same_row = obstruction_row == guard.row;
+    let same_col = obstruction_col == guard.col;
+    // The extra obstruction only matters if it is ahead of the guard and before the nearest one
+    let blocker = match guard.direction {
+        Direction::Up
+            if same_col
+                && obstruction_row < guard.row
+                && nearest.is_none_or(|row| obstruction_row > row) =>
+        {
+            Some(obstruction_row)
+        }
+        Direction::Down
+            if same_col
+                && obstruction_row > guard.row
+                && nearest.is_none_or(|row| obstruction_row < row) =>
+        {
+            Some(obstruction_row)
         }
-        Tile::Exit => Guard {
+        Direction::Left
+            if same_row
+                && obstruction_col < guard.col
+                && nearest.is_none_or(|col| obstruction_col > col) =>
+        {
+            Some(obstruction_col)
+        }
+        Direction::Right
+            if same_row
+                && obstruction_col > guard.col
+                && nearest.is_none_or(|col| obstruction_col < col) =>
+        {
+            Some(obstruction_col)
+        }
+        _ => nearest,
+    };
+    let Some(blocker) = blocker else {
+        return Guard {
             exited: true,
             ..guard
-        },
+        };
+    };
+    // Stop on the cell before the obstruction
+    let (row, col) = match guard.direction {
+        Direction::Up => (blocker + 1, guard.col),
+        Direction::Down => (blocker - 1, guard.col),
+        Direction::Left => (guard.row, blocker + 1),
+        Direction::Right => (guard.row, blocker - 1),
+    };
+    Guard {
+        row,
+        col,
+        direction: guard.direction.turn_right(),
+        exited: false,
+    }
+}
+
+/// Animate the guard's patrol, yielding the map after every step with the path so far.
+/// With an obstruction the patrol is replayed with it on the map until the guard exits or
+/// starts repeating its loop. Part 2 without an obstruction picks the first one that makes
+/// the guard loop. A boxed in guard is shown where it stands.
+pub fn visualize(
+    input: &str,
+    part: u8,
+    obstruction: Option<Position>,
+) -> impl Iterator<Item = String> {
+    let (mut map, starting_guard) = parse_map(input);
+    let obstruction = match obstruction {
+        Some(Position { row, col }) => Some((row, col)),
+        None if part == 2 => {
+            let jumps = JumpTable::new(&map);
+            let (obstruction, _) = first_arrivals(&map, starting_guard)
+                .unwrap_or_else(|e| panic!("{e}"))
+                .into_iter()
+                .find(|&(obstruction, guard)| guard_loops(&map, &jumps, guard, obstruction))
+                .unwrap_or_else(|| panic!("No obstruction makes the guard loop"));
+            Some(obstruction)
+        }
+        None => None,
+    };
+    if let Some((row, col)) = obstruction {
+        assert!(
+            map.get(row).and_then(|tiles| tiles.get(col)) == Some(&Tile::Empty)
+                && (row, col) != (starting_guard.row, starting_guard.col),
+            "The obstruction must be placed on an empty tile inside the map"
+        );
+        map[row][col] = Tile::Obstruction;
+    }
+
+    gen move {
+        let mut trail = vec![vec![0; map[0].len()]; map.len()];
+        let mut visited = VisitedStates::new(&map);
+        let mut current_guard = starting_guard;
+        yield print_map(&map, &current_guard, &trail);
+        while visited.insert(current_guard) {
+            let Ok(new_guard) = move_guard(&map, current_guard, &MovementPolicy::default()) else {
+                break;
+            };
+            trail[current_guard.row][current_guard.col] |=
+                trail_mark(current_guard.direction) | trail_mark(new_guard.direction);
+            if new_guard.exited {
+                yield print_map(&map, &new_guard, &trail);
+                break;
+            }
+            trail[new_guard.row][new_guard.col] |= trail_mark(new_guard.direction);
+            current_guard = new_guard;
+            yield print_map(&map, &current_guard, &trail);
+        }
+    }
+}
+
+/// The trail mark left by the guard walking in the direction
+fn trail_mark(direction: Direction) -> u8 {
+    match direction {
+        Direction::Up | Direction::Down => VERTICAL,
+        Direction::Left | Direction::Right => HORIZONTAL,
+    }
+}
+
+/// Count the tiles visited by the guards moving by the policy. A guard caught in a loop
+/// stops once it has gone around it.
+pub fn solution(input: &str, policy: &MovementPolicy) -> Result<usize, PatrolError> {
+    let (map, guards) = parse_guards(input);
+    assert!(
+        policy.multiple_guards || guards.len() == 1,
+        "Found {} guards, but the policy only allows one",
+        guards.len()
+    );
+    let mut visited = vec![vec![false; map[0].len()]; map.len()];
+    for guard in guards {
+        visited[guard.row][guard.col] = true;
+        let mut states = VisitedStates::new(&map);
+        let mut current_guard = guard;
+        while states.insert(current_guard) {
+            current_guard = move_guard(&map, current_guard, policy)?;
+            if current_guard.exited {
+                break;
+            }
+            visited[current_guard.row][current_guard.col] = true;
+        }
+    }
+    Ok(visited.iter().flatten().filter(|&&visited| visited).count())
+}
+
+/// Move the guard one step according to the direction, the map and the policy.
+/// A blocked guard turns on the spot until it can step, trying each direction the policy
+/// lets it turn to once. If none are free the guard is boxed in.
+fn move_guard(map: &Map, guard: Guard, policy: &MovementPolicy) -> Result<Guard, PatrolError> {
+    let mut direction = guard.direction;
+    loop {
+        let Some((row, col)) = next_cell(map, guard.row, guard.col, direction, policy.wrap) else {
+            return Ok(Guard {
+                direction,
+                exited: true,
+                ..guard
+            });
+        };
+        let blocked = match map[row][col] {
+            Tile::Empty => false,
+            Tile::Obstruction => true,
+            Tile::OneWay(entry) => policy.one_way && entry != direction,
+        };
+        if !blocked {
+            return Ok(Guard {
+                row,
+                col,
+                direction,
+                ..guard
+            });
+        }
+        direction = policy.turn.apply(direction);
+        if direction == guard.direction {
+            return Err(PatrolError::BoxedIn(Position {
+                row: guard.row,
+                col: guard.col,
+            }));
+        }
+    }
+}
+
+/// The cell next to the given one in the direction, or `None` if that is off the map.
+/// With `wrap` the edges of the map lead round to the opposite edge.
+fn next_cell(
+    map: &Map,
+    row: usize,
+    col: usize,
+    direction: Direction,
+    wrap: bool,
+) -> Option<(usize, usize)> {
+    let (rows, cols) = (map.len(), map[0].len());
+    match direction {
+        Direction::Up => Some((row.checked_sub(1).or(wrap.then(|| rows - 1))?, col)),
+        Direction::Down => Some((
+            (row + 1 < rows).then_some(row + 1).or(wrap.then_some(0))?,
+            col,
+        )),
+        Direction::Left => Some((row, col.checked_sub(1).or(wrap.then(|| cols - 1))?)),
+        Direction::Right => Some((
+            row,
+            (col + 1 < cols).then_some(col + 1).or(wrap.then_some(0))?,
+        )),
     }
 }
 
@@ -137,60 +686,72 @@ fn parse_tile(input: char) -> (Tile, Option<Direction>) {
         '>' => (Tile::Empty, Some(Direction::Right)),
         '<' => (Tile::Empty, Some(Direction::Left)),
         '#' => (Tile::Obstruction, None),
+        'U' => (Tile::OneWay(Direction::Up), None),
+        'D' => (Tile::OneWay(Direction::Down), None),
+        'L' => (Tile::OneWay(Direction::Left), None),
+        'R' => (Tile::OneWay(Direction::Right), None),
         _ => panic!("Invalid tile: {}", input),
     }
 }
 
-/// Parse a row of tiles and capture the column index of the guard if he is present.
-/// Include the exit tiles on both sides of the row.
-fn parse_row(input_row: &str) -> (Vec<Tile>, Option<(Direction, usize)>) {
-    let (mut row, guard_direction) = input_row.chars().enumerate().fold(
-        (vec![Tile::Exit], None),
-        |(mut row, mut direction), (col_index, tile)| {
-            let (tile, guard_direction) = parse_tile(tile);
-            row.push(tile);
-            if guard_direction.is_some() {
-                direction = guard_direction.map(|direction| (direction, col_index));
-            }
-            (row, direction)
-        },
-    );
-    row.push(Tile::Exit);
-    (row, guard_direction)
+/// Parse a row of tiles and capture the column index of each guard present.
+fn parse_row(input_row: &str) -> (Vec<Tile>, Vec<(Direction, usize)>) {
+    let mut row = Vec::new();
+    let mut guards = Vec::new();
+    for (col_index, tile) in input_row.chars().enumerate() {
+        let (tile, guard_direction) = parse_tile(tile);
+        row.push(tile);
+        if let Some(direction) = guard_direction {
+            guards.push((direction, col_index));
+        }
+    }
+    (row, guards)
 }
 
-/// Parse the map and the guard. Include the exit tiles on the top and bottom of the map.
+/// Parse the map and the single guard on it.
 fn parse_map(input: &str) -> (Vec<Vec<Tile>>, Guard) {
-    let mut rows = input.lines().peekable();
-    let width = rows.peek().unwrap().chars().count() + 2;
-    let mut map = vec![vec![Tile::Exit; width]];
-    let mut guard = None;
+    let (map, guards) = parse_guards(input);
+    assert_eq!(guards.len(), 1, "Expected a single guard on the map");
+    (map, guards[0])
+}
+
+/// Parse the map and every guard on it.
+fn parse_guards(input: &str) -> (Vec<Vec<Tile>>, Vec<Guard>) {
+    let mut map = Vec::new();
+    let mut guards = Vec::new();
     for (row_index, row) in input.lines().enumerate() {
-        let (tiles, guard_direction) = parse_row(row);
+        let (tiles, row_guards) = parse_row(row);
         map.push(tiles);
-        if let Some((direction, col)) = guard_direction {
-            guard = Some(Guard {
-                // Account for added exit tiles
-                row: row_index + 1,
-                col: col + 1,
+        for (direction, col) in row_guards {
+            guards.push(Guard {
+                row: row_index,
+                col,
                 direction,
                 exited: false,
             });
         }
     }
-    map.push(vec![Tile::Exit; width]);
-    (map, guard.unwrap())
+    (map, guards)
 }
 
-/// Debugging function to print the map with the guard's position and direction.
-fn print_map(map: &Map, guard_position: &Guard) -> String {
+/// Print the map with the guard's position and direction, and the trail it has left.
+/// An exited guard isn't drawn.
+fn print_map(map: &Map, guard_position: &Guard, trail: &[Vec<u8>]) -> String {
     let mut result = String::new();
     for (row_index, row) in map.iter().enumerate() {
         for (col_index, tile) in row.iter().enumerate() {
-            if row_index == guard_position.row && col_index == guard_position.col {
+            if row_index == guard_position.row
+                && col_index == guard_position.col
+                && !guard_position.exited
+            {
                 result.push(guard_position.direction.to_string().chars().next().unwrap());
             } else {
-                result.push(tile.to_string().chars().next().unwrap());
+                result.push(match trail[row_index][col_index] {
+                    VERTICAL => '|',
+                    HORIZONTAL => '-',
+                    0 => tile.to_string().chars().next().unwrap(),
+                    _ => '+',
+                });
             }
         }
         if row_index < map.len() - 1 {
@@ -232,6 +793,101 @@ mod tests {
         b.iter(|| part_2(&input));
     }
 
+    const EXAMPLE: &str = "....#.....
+.........#
+..........
+..#.......
+.......#..
+..........
+.#..^.....
+........#.
+#.........
+......#...";
+
+    #[test]
+    fn test_jump_guard() {
+        let (map, guard) = parse_map(EXAMPLE);
+        let jumps = JumpTable::new(&map);
+        let no_obstruction = (usize::MAX, usize::MAX);
+        let guard = jump_guard(&jumps, guard, no_obstruction);
+        assert_eq!(
+            (guard.row, guard.col, guard.direction),
+            (1, 4, Direction::Right)
+        );
+        let guard = jump_guard(&jumps, guard, no_obstruction);
+        assert_eq!(
+            (guard.row, guard.col, guard.direction),
+            (1, 8, Direction::Down)
+        );
+        // An obstruction in the way stops the guard early
+        let stopped = jump_guard(&jumps, guard, (4, 8));
+        assert_eq!(
+            (stopped.row, stopped.col, stopped.direction),
+            (3, 8, Direction::Left)
+        );
+        // An obstruction behind the guard doesn't
+        let guard = jump_guard(&jumps, guard, (0, 8));
+        assert_eq!(
+            (guard.row, guard.col, guard.direction),
+            (6, 8, Direction::Left)
+        );
+    }
+
+    #[test]
+    fn test_jump_guard_exit() {
+        let (map, guard) = parse_map(EXAMPLE);
+        let jumps = JumpTable::new(&map);
+        let no_obstruction = (usize::MAX, usize::MAX);
+        let guard = Guard {
+            direction: Direction::Down,
+            ..guard
+        };
+        assert!(jump_guard(&jumps, guard, no_obstruction).exited);
+        assert!(!jump_guard(&jumps, guard, (9, 4)).exited);
+    }
+
+    #[test]
+    fn test_first_arrivals() {
+        let (map, guard) = parse_map(EXAMPLE);
+        let arrivals = first_arrivals(&map, guard).unwrap();
+        assert_eq!(arrivals.len(), 40);
+        assert_eq!(arrivals[0], ((5, 4), guard));
+        // The guard turns on (1, 4) before first entering (1, 5)
+        let (cell, before) = arrivals[5];
+        assert_eq!(cell, (1, 5));
+        assert_eq!(
+            (before.row, before.col, before.direction),
+            (1, 4, Direction::Right)
+        );
+    }
+
+    #[test]
+    fn test_visited_states() {
+        let (map, guard) = parse_map(EXAMPLE);
+        let mut visited = VisitedStates::new(&map);
+        assert!(visited.insert(guard));
+        assert!(!visited.insert(guard));
+        let turned = Guard {
+            direction: Direction::Right,
+            ..guard
+        };
+        assert!(visited.insert(turned));
+    }
+
+    #[test]
+    fn test_guard_loops() {
+        let (map, guard) = parse_map(EXAMPLE);
+        let jumps = JumpTable::new(&map);
+        let loops: Vec<(usize, usize)> = (0..10)
+            .flat_map(|row| (0..10).map(move |col| (row, col)))
+            .filter(|&(row, col)| {
+                map[row][col] == Tile::Empty && (row, col) != (guard.row, guard.col)
+            })
+            .filter(|&obstruction| guard_loops(&map, &jumps, guard, obstruction))
+            .collect();
+        assert_eq!(loops, vec![(6, 3), (7, 6), (7, 7), (8, 1), (8, 3), (9, 7)]);
+    }
+
     #[test]
     fn test_parse_map_and_display() {
         let input = [
@@ -249,21 +905,245 @@ mod tests {
         .join("\n");
 
         let expected = [
-            "EEEEEEEEEEEE",
-            "E....#.....E",
-            "E.........#E",
-            "E..........E",
-            "E..#.......E",
-            "E.......#..E",
-            "E..........E",
-            "E.#..^.....E",
-            "E........#.E",
-            "E#.........E",
-            "E......#...E",
-            "EEEEEEEEEEEE",
+            "....#.....",
+            ".........#",
+            "..........",
+            "..#.......",
+            ".......#..",
+            "..........",
+            ".#..^.....",
+            "........#.",
+            "#.........",
+            "......#...",
         ]
         .join("\n");
         let (map, guard_position) = parse_map(&input);
-        assert_eq!(print_map(&map, &guard_position).to_string(), expected);
+        let trail = vec![vec![0; map[0].len()]; map.len()];
+        assert_eq!(print_map(&map, &guard_position, &trail), expected);
+    }
+
+    #[test]
+    fn test_visualize() {
+        let last_frame = visualize(EXAMPLE, 1, None).last().unwrap();
+        let expected = [
+            "....#.....",
+            "....+---+#",
+            "....|...|.",
+            "..#.|...|.",
+            "..+-+-+#|.",
+            "..|.|.|.|.",
+            ".#+-+-+-+.",
+            ".+----++#.",
+            "#+----+|..",
+            "......#|..",
+        ]
+        .join("\n");
+        assert_eq!(last_frame, expected);
+        assert_eq!(visualize(EXAMPLE, 1, None).count(), 46);
+    }
+
+    #[test]
+    fn test_visualize_loop() {
+        let obstruction = "6,3".parse().unwrap();
+        let last_frame = visualize(EXAMPLE, 2, Some(obstruction)).last().unwrap();
+        let expected = [
+            "....#.....",
+            "....+---+#",
+            "....|...|.",
+            "..#.|...|.",
+            "....|..#|.",
+            "....^...|.",
+            ".#.#+---+.",
+            "........#.",
+            "#.........",
+            "......#...",
+        ]
+        .join("\n");
+        assert_eq!(last_frame, expected);
+        // Without an obstruction part 2 replays the first loop found
+        assert!(
+            visualize(EXAMPLE, 2, None)
+                .last()
+                .unwrap()
+                .contains(".#.#+---+.")
+        );
+    }
+
+    #[test]
+    fn test_patrol() {
+        let patrol = patrol(EXAMPLE).unwrap();
+        assert_eq!(patrol.states.len(), 45);
+        assert_eq!(patrol.states[0], GuardState {
+            position: Position { row: 6, col: 4 },
+            direction: Direction::Up,
+        });
+        assert_eq!(patrol.states.last().unwrap().position, Position {
+            row: 9,
+            col: 7
+        });
+        let turns: Vec<_> = patrol
+            .turns
+            .iter()
+            .map(|turn| (turn.row, turn.col))
+            .collect();
+        assert_eq!(turns, vec![
+            (1, 4),
+            (1, 8),
+            (4, 2),
+            (4, 6),
+            (6, 2),
+            (6, 8),
+            (7, 1),
+            (7, 7),
+            (8, 1),
+            (8, 6)
+        ]);
+        let loops: Vec<_> = patrol
+            .loops
+            .iter()
+            .map(|cycle| (cycle.obstruction.row, cycle.obstruction.col, cycle.length))
+            .collect();
+        // Obstructions are listed in the order the guard reaches them
+        assert_eq!(loops, vec![
+            (6, 3, 18),
+            (7, 6, 12),
+            (8, 3, 34),
+            (8, 1, 16),
+            (7, 7, 12),
+            (9, 7, 14)
+        ]);
+    }
+
+    #[test]
+    fn test_patrol_json() {
+        let patrol = Patrol {
+            states: vec![GuardState {
+                position: Position { row: 1, col: 2 },
+                direction: Direction::Left,
+            }],
+            turns: BTreeSet::from([Position { row: 1, col: 2 }]),
+            loops: vec![Loop {
+                obstruction: Position { row: 0, col: 1 },
+                length: 8,
+            }],
+        };
+        assert_eq!(
+            patrol.to_json(),
+            r#"{"states":[{"row":1,"col":2,"direction":"<"}],"turns":[{"row":1,"col":2}],"loops":[{"obstruction":{"row":0,"col":1},"length":8}]}"#
+        );
+    }
+
+    #[test]
+    fn test_parse_movement_policy() {
+        assert_eq!("".parse(), Ok(MovementPolicy::default()));
+        assert_eq!(
+            "turn=left, wrap,one-way,guards".parse(),
+            Ok(MovementPolicy {
+                turn: Turn::Left,
+                wrap: true,
+                one_way: true,
+                multiple_guards: true,
+            })
+        );
+        assert!("turn=around".parse::<MovementPolicy>().is_err());
+        assert!("teleport".parse::<MovementPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_solution_turns() {
+        let input = ".#...\n.....\n.^...";
+        let policy = |s: &str| s.parse::<MovementPolicy>().unwrap();
+        assert_eq!(solution(input, &policy("turn=right")).unwrap(), 5);
+        assert_eq!(solution(input, &policy("turn=left")).unwrap(), 3);
+        assert_eq!(solution(input, &policy("turn=reverse")).unwrap(), 2);
+        assert_eq!(solution(EXAMPLE, &MovementPolicy::default()).unwrap(), 41);
+    }
+
+    #[test]
+    fn test_solution_wrap() {
+        // The guard ends up walking along the middle row forever
+        let input = ".#.\n...\n.^.";
+        let policy = "wrap".parse().unwrap();
+        assert_eq!(solution(input, &MovementPolicy::default()).unwrap(), 3);
+        assert_eq!(solution(input, &policy).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_solution_one_way() {
+        let input = "...\n.L.\n.^.";
+        let policy = "one-way".parse().unwrap();
+        assert_eq!(solution(input, &MovementPolicy::default()).unwrap(), 3);
+        assert_eq!(solution(input, &policy).unwrap(), 2);
+        assert_eq!(solution("...\n.U.\n.^.", &policy).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_solution_multiple_guards() {
+        let input = "^..\n..v";
+        let policy = "guards".parse().unwrap();
+        assert_eq!(solution(input, &policy).unwrap(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Found 2 guards")]
+    fn test_solution_multiple_guards_not_allowed() {
+        let _ = solution("^..\n..v", &MovementPolicy::default());
+    }
+
+    #[test]
+    fn test_boxed_in() {
+        let input = ".#.\n#^#\n.#.";
+        assert_eq!(
+            solution(input, &MovementPolicy::default()),
+            Err(PatrolError::BoxedIn(Position { row: 1, col: 1 }))
+        );
+        // Reversing only needs the way ahead and behind to be blocked
+        let input = "...\n.^.\n...";
+        let (map, guard) = parse_map(input);
+        let policy = "turn=reverse".parse().unwrap();
+        let boxed_in = parse_map(".#.\n.^.\n.#.").0;
+        assert!(move_guard(&map, guard, &policy).is_ok());
+        assert!(move_guard(&boxed_in, guard, &policy).is_err());
+    }
+
+    #[test]
+    fn test_patrol_loops() {
+        // Without any extra obstruction this guard already walks in a loop
+        let input = ".#...\n.^..#\n#....\n...#.";
+        assert_eq!(solution(input, &MovementPolicy::default()), Ok(6));
+        assert_eq!(patrol(input).unwrap_err(), PatrolError::Loops);
+        let (map, guard) = parse_map(input);
+        assert_eq!(first_arrivals(&map, guard), Err(PatrolError::Loops));
+    }
+
+    #[test]
+    fn test_move_guard_edges() {
+        // A guard on the edge of an unpadded map leaves it without underflowing
+        let (map, guard) = parse_map("^..\n...");
+        let exited = move_guard(&map, guard, &MovementPolicy::default()).unwrap();
+        assert!(exited.exited);
+        let wrapped = move_guard(&map, guard, &"wrap".parse().unwrap()).unwrap();
+        assert_eq!((wrapped.row, wrapped.col, wrapped.exited), (1, 0, false));
+    }
+
+    #[test]
+    fn test_next_cell() {
+        let (map, _) = parse_map("^..\n...");
+        assert_eq!(next_cell(&map, 0, 0, Direction::Up, false), None);
+        assert_eq!(next_cell(&map, 0, 0, Direction::Left, false), None);
+        assert_eq!(next_cell(&map, 1, 2, Direction::Down, false), None);
+        assert_eq!(next_cell(&map, 1, 2, Direction::Right, false), None);
+        assert_eq!(next_cell(&map, 0, 0, Direction::Right, false), Some((0, 1)));
+        assert_eq!(next_cell(&map, 0, 0, Direction::Up, true), Some((1, 0)));
+        assert_eq!(next_cell(&map, 0, 0, Direction::Left, true), Some((0, 2)));
+        assert_eq!(next_cell(&map, 1, 2, Direction::Down, true), Some((0, 2)));
+        assert_eq!(next_cell(&map, 1, 2, Direction::Right, true), Some((1, 0)));
+    }
+
+    #[test]
+    fn test_parse_position() {
+        assert_eq!("6,3".parse(), Ok(Position { row: 6, col: 3 }));
+        assert!("6".parse::<Position>().is_err());
+        assert!("6,x".parse::<Position>().is_err());
     }
 }
@@ -1,4 +1,9 @@
-use std::fmt::Debug;
+use std::{
+    fmt::{Debug, Display, Write as _},
+    ops::ControlFlow,
+    str::FromStr,
+    sync::Arc,
+};
 
 use nom::{
     IResult, bytes::complete::tag, character::complete::space1, multi::separated_list1,
@@ -14,69 +19,682 @@ struct Equation {
     test_value: usize,
 }
 
+/// An operator that can be placed between the values of an equation. Its `Display` is the
+/// symbol it is written with.
+pub trait Operator: Debug + Display + Send + Sync {
+    /// Combine the running total with the next value, or `None` if the result isn't a
+    /// natural number or doesn't fit in a `usize`
+    fn apply(&self, left: usize, right: usize) -> Option<usize>;
+
+    /// Whether `inverse` undoes the operator with this right operand, so equations can be
+    /// solved backwards from the test value
+    fn invertible(&self, _right: usize) -> bool {
+        false
+    }
+
+    /// The only left operand that gives `result` when combined with `right`, if there is one.
+    /// Only used when the operator is `invertible`.
+    fn inverse(&self, _result: usize, _right: usize) -> Option<usize> {
+        None
+    }
+
+    /// How tightly the operator binds when evaluating with precedence. Operators with a
+    /// higher precedence are applied first, and equal ones from left to right.
+    fn precedence(&self) -> u8 {
+        ADDITIVE
+    }
+}
+
+/// Precedence of addition and subtraction
+pub const ADDITIVE: u8 = 1;
+/// Precedence of multiplication and division
+pub const MULTIPLICATIVE: u8 = 2;
+/// Precedence of exponentiation
+pub const EXPONENTIAL: u8 = 3;
+/// Precedence of concatenation, which joins the digits before anything else
+pub const CONCATENATION: u8 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Add;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Multiply;
+
+/// Write the digits of the right value after those of the left, in the base
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Operator {
-    Add,
-    Multiply,
-    Concat,
+pub struct Concat {
+    pub base: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Subtract;
+
+/// Integer division, rounding down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Divide;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Power;
+
+/// An operator with its precedence changed, e.g. to make concatenation bind like addition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WithPrecedence<O> {
+    pub operator: O,
+    pub precedence: u8,
+}
+
+/// Where concatenation sits among the other operators when evaluating with precedence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConcatPrecedence {
+    Additive,
+    Multiplicative,
+    Highest,
+}
+
+/// The operators allowed in part 1
+pub const ARITHMETIC: &[&dyn Operator] = &[&Add, &Multiply];
+
+/// The operators allowed in part 2
+pub const WITH_CONCAT: &[&dyn Operator] = &[&Add, &Multiply, &Concat { base: 10 }];
+
+/// A set of operators given by their symbols, such as `+,*,||` or `-,/,^,||2`.
+/// Concatenation in a base other than ten is written with the base after `||`.
+#[derive(Debug, Clone)]
+pub struct OperatorSet(pub Vec<Arc<dyn Operator>>);
+
+/// An equation that can be made true, with the operators that do so
+#[derive(Debug, Clone)]
+pub struct Calibration<'a> {
+    pub test_value: usize,
+    pub values: Vec<usize>,
+    /// The first satisfying sequence of operators, or all of them
+    pub assignments: Vec<Vec<&'a dyn Operator>>,
+    /// How many distinct sequences of operators satisfy the equation
+    pub count: usize,
+}
+
+impl Operator for Add {
+    fn apply(&self, left: usize, right: usize) -> Option<usize> {
+        left.checked_add(right)
+    }
+
+    fn invertible(&self, _right: usize) -> bool {
+        true
+    }
+
+    fn inverse(&self, result: usize, right: usize) -> Option<usize> {
+        result.checked_sub(right)
+    }
+}
+
+impl Operator for Multiply {
+    fn apply(&self, left: usize, right: usize) -> Option<usize> {
+        left.checked_mul(right)
+    }
+
+    fn precedence(&self) -> u8 {
+        MULTIPLICATIVE
+    }
+
+    // Anything multiplied by zero is zero, so the left operand can't be recovered
+    fn invertible(&self, right: usize) -> bool {
+        right != 0
+    }
+
+    fn inverse(&self, result: usize, right: usize) -> Option<usize> {
+        (right != 0 && result % right == 0).then(|| result / right)
+    }
+}
+
+impl Concat {
+    /// The power of the base to shift the left value by to make room for the right value,
+    /// or `None` if it doesn't fit in a `usize`. Zero is written with a single digit.
+    fn shift(&self, right: usize) -> Option<usize> {
+        let digits = right.checked_ilog(self.base).map_or(1, |log| log + 1);
+        self.base.checked_pow(digits)
+    }
+}
+
+impl Operator for Concat {
+    fn apply(&self, left: usize, right: usize) -> Option<usize> {
+        match self.shift(right) {
+            Some(shift) => left.checked_mul(shift)?.checked_add(right),
+            // The right value uses every digit, so only a leading zero leaves it unchanged
+            None => (left == 0).then_some(right),
+        }
+    }
+
+    fn invertible(&self, _right: usize) -> bool {
+        true
+    }
+
+    fn inverse(&self, result: usize, right: usize) -> Option<usize> {
+        match self.shift(right) {
+            Some(shift) => (result % shift == right).then(|| result / shift),
+            None => (result == right).then_some(0),
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        CONCATENATION
+    }
+}
+
+impl Operator for Subtract {
+    fn apply(&self, left: usize, right: usize) -> Option<usize> {
+        left.checked_sub(right)
+    }
+
+    fn invertible(&self, _right: usize) -> bool {
+        true
+    }
+
+    fn inverse(&self, result: usize, right: usize) -> Option<usize> {
+        result.checked_add(right)
+    }
+}
+
+// Division rounds down, so many left operands give the same result and it can't be inverted
+impl Operator for Divide {
+    fn apply(&self, left: usize, right: usize) -> Option<usize> {
+        left.checked_div(right)
+    }
+
+    fn precedence(&self) -> u8 {
+        MULTIPLICATIVE
+    }
+}
+
+impl Operator for Power {
+    fn apply(&self, left: usize, right: usize) -> Option<usize> {
+        left.checked_pow(u32::try_from(right).ok()?)
+    }
+
+    fn precedence(&self) -> u8 {
+        EXPONENTIAL
+    }
+}
+
+impl<O: Operator> Operator for WithPrecedence<O> {
+    fn apply(&self, left: usize, right: usize) -> Option<usize> {
+        self.operator.apply(left, right)
+    }
+
+    fn invertible(&self, right: usize) -> bool {
+        self.operator.invertible(right)
+    }
+
+    fn inverse(&self, result: usize, right: usize) -> Option<usize> {
+        self.operator.inverse(result, right)
+    }
+
+    fn precedence(&self) -> u8 {
+        self.precedence
+    }
+}
+
+impl Display for Add {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "+")
+    }
+}
+
+impl Display for Multiply {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "*")
+    }
+}
+
+impl Display for Concat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.base == 10 {
+            write!(f, "||")
+        } else {
+            write!(f, "||{}", self.base)
+        }
+    }
+}
+
+impl Display for Subtract {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "-")
+    }
+}
+
+impl Display for Divide {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "/")
+    }
+}
+
+impl Display for Power {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "^")
+    }
+}
+
+impl<O: Display> Display for WithPrecedence<O> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.operator.fmt(f)
+    }
+}
+
+impl ConcatPrecedence {
+    pub fn level(self) -> u8 {
+        match self {
+            ConcatPrecedence::Additive => ADDITIVE,
+            ConcatPrecedence::Multiplicative => MULTIPLICATIVE,
+            ConcatPrecedence::Highest => CONCATENATION,
+        }
+    }
+}
+
+/// Parse `add`, `multiply` or `highest`
+impl FromStr for ConcatPrecedence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "add" => Ok(ConcatPrecedence::Additive),
+            "multiply" => Ok(ConcatPrecedence::Multiplicative),
+            "highest" => Ok(ConcatPrecedence::Highest),
+            _ => Err(format!("Invalid concatenation precedence: {s}")),
+        }
+    }
+}
+
+impl OperatorSet {
+    pub fn operators(&self) -> Vec<&dyn Operator> {
+        self.0.iter().map(|operator| operator.as_ref()).collect()
+    }
+}
+
+impl FromStr for OperatorSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let operators = s
+            .split(',')
+            .map(str::trim)
+            .map(|symbol| -> Result<Arc<dyn Operator>, String> {
+                Ok(match symbol {
+                    "+" => Arc::new(Add),
+                    "*" => Arc::new(Multiply),
+                    "-" => Arc::new(Subtract),
+                    "/" => Arc::new(Divide),
+                    "^" => Arc::new(Power),
+                    "||" => Arc::new(Concat { base: 10 }),
+                    _ => {
+                        let base = symbol
+                            .strip_prefix("||")
+                            .and_then(|base| base.parse().ok())
+                            .filter(|&base| base >= 2)
+                            .ok_or_else(|| format!("Invalid operator: {symbol}"))?;
+                        Arc::new(Concat { base })
+                    }
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self(operators))
+    }
+}
+
+impl Calibration<'_> {
+    /// Write the equation with the operators filled in, e.g. `190 = 10 * 19`
+    pub fn expression(&self, operators: &[&dyn Operator]) -> String {
+        let mut expression = format!("{} = {}", self.test_value, self.values[0]);
+        for (value, operator) in self.values[1..].iter().zip(operators) {
+            let _ = write!(expression, " {operator} {value}");
+        }
+        expression
+    }
 }
 
 pub fn part_1(input: &str) -> usize {
-    let equations = input.lines().map(parse_equation).collect::<Vec<_>>();
-    solution(&equations, &[Operator::Add, Operator::Multiply])
+    total(input, ARITHMETIC)
 }
 
 pub fn part_2(input: &str) -> usize {
+    total(input, WITH_CONCAT)
+}
+
+/// Sum the test values of the equations that can be made true with the operators
+pub fn total(input: &str, operators: &[&dyn Operator]) -> usize {
     let equations: Vec<Equation> = input.lines().map(parse_equation).collect();
-    solution(&equations, &[
-        Operator::Add,
-        Operator::Multiply,
-        Operator::Concat,
-    ])
+    solution(&equations, operators)
 }
 
-fn solution(equations: &[Equation], operators: &[Operator]) -> usize {
+/// Sum the test values of the equations that can be made true with the operators when
+/// they are evaluated by precedence instead of from left to right
+pub fn total_with_precedence(input: &str, operators: &[&dyn Operator]) -> usize {
+    let equations: Vec<Equation> = input.lines().map(parse_equation).collect();
     equations
         .par_iter()
+        .filter(|Equation { values, test_value }| {
+            let sequence = solve_with_precedence(values, *test_value, operators);
+            debug_assert!(
+                sequence.as_ref().is_none_or(
+                    |sequence| evaluate_with_precedence(values, sequence) == Some(*test_value)
+                )
+            );
+            sequence.is_some()
+        })
+        .map(|equation| equation.test_value)
+        .sum()
+}
+
+/// Find the equations that can be made true with the operators, along with the first
+/// satisfying sequence of operators or, with `all`, every one of them
+pub fn calibrations<'a>(
+    input: &str,
+    operators: &[&'a dyn Operator],
+    all: bool,
+) -> Vec<Calibration<'a>> {
+    input
+        .lines()
+        .map(parse_equation)
         .filter_map(|Equation { values, test_value }| {
-            for combo in combinations(values.len() - 1, operators) {
-                if evaluate(values, &combo) == *test_value {
-                    return Some(*test_value);
-                }
+            let assignments = if all {
+                solve_all(&values, test_value, operators)
+            } else {
+                solve(&values, test_value, operators).into_iter().collect()
+            };
+            if assignments.is_empty() {
+                return None;
             }
-            None
+            let count = count_solutions(&values, test_value, operators);
+            Some(Calibration {
+                test_value,
+                values,
+                assignments,
+                count,
+            })
         })
+        .collect()
+}
+
+/// List the satisfying expressions for each equation that can be made true, one per line
+pub fn explain(input: &str, operators: &[&dyn Operator], all: bool) -> String {
+    calibrations(input, operators, all)
+        .iter()
+        .flat_map(|calibration| {
+            calibration
+                .assignments
+                .iter()
+                .enumerate()
+                .map(move |(index, assignment)| (calibration, index, assignment))
+        })
+        .fold(
+            String::new(),
+            |mut output, (calibration, index, assignment)| {
+                let _ = writeln!(
+                    output,
+                    "{} ({} of {})",
+                    calibration.expression(assignment),
+                    index + 1,
+                    calibration.count
+                );
+                output
+            },
+        )
+}
+
+fn solution(equations: &[Equation], operators: &[&dyn Operator]) -> usize {
+    equations
+        .par_iter()
+        .filter(|Equation { values, test_value }| {
+            let sequence = solve(values, *test_value, operators);
+            debug_assert!(
+                sequence
+                    .as_ref()
+                    .is_none_or(|sequence| evaluate(values, sequence) == Some(*test_value))
+            );
+            sequence.is_some()
+        })
+        .map(|equation| equation.test_value)
         .sum()
 }
 
-fn evaluate(values: &[usize], operators: &[Operator]) -> usize {
+/// Find the first sequence of operators that makes the values evaluate to the test value
+fn solve<'a>(
+    values: &[usize],
+    test_value: usize,
+    operators: &[&'a dyn Operator],
+) -> Option<Vec<&'a dyn Operator>> {
+    let mut solution = None;
+    search(values, test_value, operators, &mut |sequence| {
+        solution = Some(sequence.to_vec());
+        ControlFlow::Break(())
+    });
+    solution
+}
+
+/// Find every sequence of operators that makes the values evaluate to the test value
+fn solve_all<'a>(
+    values: &[usize],
+    test_value: usize,
+    operators: &[&'a dyn Operator],
+) -> Vec<Vec<&'a dyn Operator>> {
+    let mut solutions = Vec::new();
+    search(values, test_value, operators, &mut |sequence| {
+        solutions.push(sequence.to_vec());
+        ControlFlow::Continue(())
+    });
+    solutions
+}
+
+/// Count the sequences of operators that make the values evaluate to the test value
+/// without building them
+fn count_solutions(values: &[usize], test_value: usize, operators: &[&dyn Operator]) -> usize {
+    let mut count = 0;
+    search(values, test_value, operators, &mut |_| {
+        count += 1;
+        ControlFlow::Continue(())
+    });
+    count
+}
+
+/// Call `found` with each sequence of operators that makes the values evaluate to the test
+/// value, until it breaks. The search runs backwards from the test value if every operator
+/// can be undone with every value, and forwards from the first value otherwise. Either way
+/// a branch is dropped as soon as it would overflow.
+fn search<'a>(
+    values: &[usize],
+    test_value: usize,
+    operators: &[&'a dyn Operator],
+    found: &mut dyn FnMut(&[&'a dyn Operator]) -> ControlFlow<()>,
+) {
+    let Some(&placeholder) = operators.first() else {
+        if values == [test_value] {
+            let _ = found(&[]);
+        }
+        return;
+    };
+    let Some((&first, rest)) = values.split_first() else {
+        return;
+    };
+    let mut sequence = vec![placeholder; rest.len()];
+    let invertible = rest
+        .iter()
+        .all(|&value| operators.iter().all(|operator| operator.invertible(value)));
+    if invertible {
+        let _ = search_backwards(values, test_value, operators, &mut sequence, found);
+    } else {
+        let _ = search_forwards(first, rest, test_value, operators, &mut sequence, found);
+    }
+}
+
+/// Work backwards from the test value, undoing the operator on the last value at each step.
+/// Operators that can't have produced the value are pruned straight away, such as a
+/// multiplication that doesn't divide exactly or a concatenation that doesn't end in the
+/// last value.
+fn search_backwards<'a>(
+    values: &[usize],
+    test_value: usize,
+    operators: &[&'a dyn Operator],
+    sequence: &mut [&'a dyn Operator],
+    found: &mut dyn FnMut(&[&'a dyn Operator]) -> ControlFlow<()>,
+) -> ControlFlow<()> {
+    let (&last, rest) = values.split_last().unwrap();
+    if rest.is_empty() {
+        return if last == test_value {
+            found(sequence)
+        } else {
+            ControlFlow::Continue(())
+        };
+    }
+    for &operator in operators {
+        if let Some(remaining) = operator.inverse(test_value, last) {
+            sequence[rest.len() - 1] = operator;
+            search_backwards(rest, remaining, operators, sequence, found)?;
+        }
+    }
+    ControlFlow::Continue(())
+}
+
+/// Work forwards from the running total, trying every operator on the next value
+fn search_forwards<'a>(
+    total: usize,
+    values: &[usize],
+    test_value: usize,
+    operators: &[&'a dyn Operator],
+    sequence: &mut [&'a dyn Operator],
+    found: &mut dyn FnMut(&[&'a dyn Operator]) -> ControlFlow<()>,
+) -> ControlFlow<()> {
+    let Some((&next, rest)) = values.split_first() else {
+        return if total == test_value {
+            found(sequence)
+        } else {
+            ControlFlow::Continue(())
+        };
+    };
+    let index = sequence.len() - values.len();
+    for &operator in operators {
+        if let Some(total) = operator.apply(total, next) {
+            sequence[index] = operator;
+            search_forwards(total, rest, test_value, operators, sequence, found)?;
+        }
+    }
+    ControlFlow::Continue(())
+}
+
+/// Part of an expression evaluated by precedence. Operators wait with their left operand
+/// until an operator that binds less tightly, or the end of the expression, shows that
+/// their right operand is complete.
+#[derive(Debug, Clone)]
+struct PrecedenceStack<'a> {
+    operands: Vec<usize>,
+    operators: Vec<&'a dyn Operator>,
+}
+
+impl<'a> PrecedenceStack<'a> {
+    fn new(first: usize) -> Self {
+        Self {
+            operands: vec![first],
+            operators: Vec::new(),
+        }
+    }
+
+    /// Add the next operator and value, first applying the waiting operators that bind at
+    /// least as tightly. Returns `None` if an operator fails.
+    fn push(&mut self, operator: &'a dyn Operator, value: usize) -> Option<()> {
+        while self
+            .operators
+            .last()
+            .is_some_and(|waiting| waiting.precedence() >= operator.precedence())
+        {
+            self.reduce()?;
+        }
+        self.operators.push(operator);
+        self.operands.push(value);
+        Some(())
+    }
+
+    /// Apply the most recent waiting operator
+    fn reduce(&mut self) -> Option<()> {
+        let operator = self.operators.pop()?;
+        let right = self.operands.pop()?;
+        let left = self.operands.pop()?;
+        self.operands.push(operator.apply(left, right)?);
+        Some(())
+    }
+
+    /// Apply every waiting operator and return the value of the expression
+    fn finish(mut self) -> Option<usize> {
+        while !self.operators.is_empty() {
+            self.reduce()?;
+        }
+        self.operands.pop()
+    }
+}
+
+/// Find the first sequence of operators that makes the values evaluate to the test value
+/// by precedence. The precedence of later operators can change how earlier ones apply,
+/// so this searches forwards, carrying the partly evaluated expression along each branch.
+fn solve_with_precedence<'a>(
+    values: &[usize],
+    test_value: usize,
+    operators: &[&'a dyn Operator],
+) -> Option<Vec<&'a dyn Operator>> {
+    let (&first, rest) = values.split_first()?;
+    let mut sequence = Vec::with_capacity(rest.len());
+    search_with_precedence(
+        PrecedenceStack::new(first),
+        rest,
+        test_value,
+        operators,
+        &mut sequence,
+    )
+    .then_some(sequence)
+}
+
+fn search_with_precedence<'a>(
+    stack: PrecedenceStack<'a>,
+    values: &[usize],
+    test_value: usize,
+    operators: &[&'a dyn Operator],
+    sequence: &mut Vec<&'a dyn Operator>,
+) -> bool {
+    let Some((&next, rest)) = values.split_first() else {
+        return stack.finish() == Some(test_value);
+    };
+    operators.iter().any(|&operator| {
+        let mut stack = stack.clone();
+        if stack.push(operator, next).is_none() {
+            return false;
+        }
+        sequence.push(operator);
+        if search_with_precedence(stack, rest, test_value, operators, sequence) {
+            return true;
+        }
+        sequence.pop();
+        false
+    })
+}
+
+/// Evaluate the expression by precedence instead of from left to right
+fn evaluate_with_precedence(values: &[usize], operators: &[&dyn Operator]) -> Option<usize> {
     debug_assert!(
-        values.len() >= 2 && operators.len() == values.len() - 1,
+        !values.is_empty() && operators.len() == values.len() - 1,
         "Invalid input"
     );
-    let mut initial = values[0];
-    for (&value, &operator) in values[1..].iter().zip(operators.iter()) {
-        match operator {
-            Operator::Add => initial += value,
-            Operator::Multiply => initial *= value,
-            Operator::Concat => initial = initial * 10_usize.pow(value.ilog10() + 1) + value,
-        }
+    let mut stack = PrecedenceStack::new(values[0]);
+    for (&value, &operator) in values[1..].iter().zip(operators) {
+        stack.push(operator, value)?;
     }
-    initial
+    stack.finish()
 }
 
-// Get the possible combinations of operators for a given number of operands
-fn combinations(n: usize, operators: &[Operator]) -> Box<dyn Iterator<Item = Vec<Operator>> + '_> {
-    if n == 0 {
-        Box::new(std::iter::once(Vec::new()))
-    } else {
-        Box::new(operators.iter().flat_map(move |&op| {
-            combinations(n - 1, operators).map(move |mut combo| {
-                combo.push(op);
-                combo
-            })
-        }))
+fn evaluate(values: &[usize], operators: &[&dyn Operator]) -> Option<usize> {
+    debug_assert!(
+        !values.is_empty() && operators.len() == values.len() - 1,
+        "Invalid input"
+    );
+    let mut initial = values[0];
+    for (&value, operator) in values[1..].iter().zip(operators.iter()) {
+        initial = operator.apply(initial, value)?;
     }
+    Some(initial)
 }
 
 fn parse_list(input: &str) -> IResult<&str, Vec<usize>> {
@@ -97,6 +715,50 @@ mod tests {
     use crate::read_input;
     use test::Bencher;
 
+    const EXAMPLE: &str = "190: 10 19
+3267: 81 40 27
+83: 17 5
+156: 15 6
+7290: 6 8 6 15
+161011: 16 10 13
+192: 17 8 14
+21037: 9 7 18 13
+292: 11 6 16 20";
+
+    fn expressions(calibration: &Calibration) -> Vec<String> {
+        calibration
+            .assignments
+            .iter()
+            .map(|assignment| calibration.expression(assignment))
+            .collect()
+    }
+
+    /// Write a sequence of operators by their symbols, e.g. `+ * ||`
+    fn symbols(sequence: &[&dyn Operator]) -> String {
+        sequence
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    // Get the possible combinations of operators for a given number of operands
+    fn combinations<'a>(
+        n: usize,
+        operators: &'a [&'a dyn Operator],
+    ) -> Box<dyn Iterator<Item = Vec<&'a dyn Operator>> + 'a> {
+        if n == 0 {
+            Box::new(std::iter::once(Vec::new()))
+        } else {
+            Box::new(operators.iter().flat_map(move |&op| {
+                combinations(n - 1, operators).map(move |mut combo| {
+                    combo.push(op);
+                    combo
+                })
+            }))
+        }
+    }
+
     #[test]
     fn test_part_1() {
         let input = read_input(7);
@@ -115,44 +777,32 @@ mod tests {
     fn bench_solution(b: &mut Bencher) {
         let input = read_input(7);
         let equations: Vec<Equation> = input.lines().map(parse_equation).collect();
-        let operators = [Operator::Add, Operator::Multiply, Operator::Concat];
 
-        b.iter(|| solution(&equations, &operators));
-    }
-
-    #[bench]
-    fn bench_combinations(b: &mut Bencher) {
-        let operators = vec![Operator::Add, Operator::Multiply, Operator::Concat];
-        b.iter(|| combinations(5, &operators).count());
+        b.iter(|| solution(&equations, WITH_CONCAT));
     }
 
     #[test]
     fn test_combinations_0() {
-        let operators = vec![Operator::Add, Operator::Multiply];
-        let combos = combinations(0, &operators).collect::<Vec<_>>();
-        assert_eq!(combos, vec![vec![]],);
+        let combos = combinations(0, ARITHMETIC).collect::<Vec<_>>();
+        assert_eq!(combos.len(), 1);
+        assert!(combos[0].is_empty());
     }
 
     #[test]
     fn test_combinations_1() {
-        let operators = vec![Operator::Add, Operator::Multiply];
-        let combos = combinations(1, &operators).collect::<Vec<_>>();
-        assert_eq!(combos, vec![vec![Operator::Add], vec![Operator::Multiply]],);
+        let combos: Vec<_> = combinations(1, ARITHMETIC)
+            .map(|combo| symbols(&combo))
+            .collect();
+        assert_eq!(combos, vec!["+", "*"]);
     }
 
     #[test]
     fn test_combinations_n() {
-        let operators = vec![Operator::Add, Operator::Multiply];
-        let combos: Vec<Vec<Operator>> = combinations(3, &operators).collect();
+        let combos: Vec<_> = combinations(3, ARITHMETIC)
+            .map(|combo| symbols(&combo))
+            .collect();
         assert_eq!(combos, vec![
-            vec![Operator::Add, Operator::Add, Operator::Add],
-            vec![Operator::Multiply, Operator::Add, Operator::Add],
-            vec![Operator::Add, Operator::Multiply, Operator::Add],
-            vec![Operator::Multiply, Operator::Multiply, Operator::Add],
-            vec![Operator::Add, Operator::Add, Operator::Multiply],
-            vec![Operator::Multiply, Operator::Add, Operator::Multiply],
-            vec![Operator::Add, Operator::Multiply, Operator::Multiply],
-            vec![Operator::Multiply, Operator::Multiply, Operator::Multiply],
+            "+ + +", "* + +", "+ * +", "* * +", "+ + *", "* + *", "+ * *", "* * *",
         ])
     }
 
@@ -169,10 +819,330 @@ mod tests {
     //     ])
     // }
 
+    #[test]
+    fn test_inverse() {
+        let concat = Concat { base: 10 };
+        assert_eq!(Add.inverse(190, 19), Some(171));
+        assert_eq!(Add.inverse(19, 190), None);
+        assert_eq!(Multiply.inverse(190, 19), Some(10));
+        assert_eq!(Multiply.inverse(190, 20), None);
+        assert_eq!(concat.inverse(156, 6), Some(15));
+        assert_eq!(concat.inverse(156, 56), Some(1));
+        assert_eq!(concat.inverse(156, 7), None);
+        assert_eq!(Subtract.inverse(5, 3), Some(8));
+    }
+
+    #[test]
+    fn test_apply() {
+        assert_eq!(Concat { base: 10 }.apply(12, 345), Some(12345));
+        // 0b101 || 0b11 = 0b10111
+        assert_eq!(Concat { base: 2 }.apply(5, 3), Some(23));
+        assert_eq!(Subtract.apply(5, 3), Some(2));
+        assert_eq!(Subtract.apply(3, 5), None);
+        assert_eq!(Divide.apply(7, 2), Some(3));
+        assert_eq!(Divide.apply(7, 0), None);
+        assert_eq!(Power.apply(2, 10), Some(1024));
+        assert_eq!(Power.apply(2, 64), None);
+    }
+
+    #[test]
+    fn test_solve() {
+        let solve = |values: &[usize], test_value, operators| {
+            solve(values, test_value, operators).map(|sequence| symbols(&sequence))
+        };
+        assert_eq!(solve(&[10, 19], 190, WITH_CONCAT).as_deref(), Some("*"));
+        assert_eq!(
+            solve(&[81, 40, 27], 3267, WITH_CONCAT).as_deref(),
+            Some("* +")
+        );
+        assert_eq!(
+            solve(&[6, 8, 6, 15], 7290, WITH_CONCAT).as_deref(),
+            Some("* || *")
+        );
+        assert_eq!(solve(&[17, 5], 83, WITH_CONCAT), None);
+        assert_eq!(solve(&[15, 6], 156, ARITHMETIC), None);
+        assert_eq!(solve(&[7], 7, &[]).as_deref(), Some(""));
+        assert_eq!(solve(&[7, 1], 7, &[]), None);
+    }
+
+    #[test]
+    fn test_solve_forwards() {
+        // Division can't be undone so these are searched forwards
+        let operators: &[&dyn Operator] = &[&Add, &Divide, &Power];
+        let solve = |values: &[usize], test_value| {
+            solve(values, test_value, operators).map(|sequence| symbols(&sequence))
+        };
+        assert_eq!(solve(&[7, 2, 3], 27), Some("/ ^".to_string()));
+        assert_eq!(solve(&[2, 3, 2], 10), Some("^ +".to_string()));
+        assert_eq!(solve(&[2, 3], 7), None);
+        assert_eq!(count_solutions(&[4, 2, 2], 4, operators), 2);
+    }
+
+    #[test]
+    fn test_solve_matches_brute_force() {
+        let operator_sets: [&[&dyn Operator]; 3] =
+            [ARITHMETIC, WITH_CONCAT, &[&Add, &Subtract, &Concat {
+                base: 10,
+            }]];
+        for equation in EXAMPLE.lines().map(parse_equation) {
+            for operators in operator_sets {
+                let Equation { values, test_value } = &equation;
+                let brute_force = combinations(values.len() - 1, operators)
+                    .filter(|combo| evaluate(values, combo) == Some(*test_value))
+                    .count();
+                assert_eq!(
+                    count_solutions(values, *test_value, operators),
+                    brute_force,
+                    "{equation:?}"
+                );
+                if let Some(sequence) = solve(values, *test_value, operators) {
+                    assert_eq!(evaluate(values, &sequence), Some(*test_value));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_solution() {
+        let equations: Vec<Equation> = EXAMPLE.lines().map(parse_equation).collect();
+        assert_eq!(solution(&equations, ARITHMETIC), 3749);
+        assert_eq!(solution(&equations, WITH_CONCAT), 11387);
+    }
+
+    #[test]
+    fn test_solve_all() {
+        let solve_all = |values: &[usize], test_value, operators| {
+            solve_all(values, test_value, operators)
+                .iter()
+                .map(|sequence| symbols(sequence))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(solve_all(&[81, 40, 27], 3267, WITH_CONCAT), vec![
+            "* +", "+ *"
+        ]);
+        assert_eq!(solve_all(&[17, 5], 83, WITH_CONCAT), Vec::<String>::new());
+        // 2 + 2 and 2 * 2 are both 4
+        assert_eq!(solve_all(&[2, 2, 4], 8, ARITHMETIC).len(), 2);
+        assert_eq!(count_solutions(&[2, 2, 4], 8, ARITHMETIC), 2);
+        assert_eq!(count_solutions(&[81, 40, 27], 3267, WITH_CONCAT), 2);
+        assert_eq!(count_solutions(&[17, 5], 83, WITH_CONCAT), 0);
+    }
+
+    #[test]
+    fn test_calibrations() {
+        let calibrations = calibrations(EXAMPLE, ARITHMETIC, false);
+        let test_values: Vec<_> = calibrations.iter().map(|c| c.test_value).collect();
+        assert_eq!(test_values, vec![190, 3267, 292]);
+        assert_eq!(expressions(&calibrations[0]), vec!["190 = 10 * 19"]);
+        assert_eq!(calibrations[1].count, 2);
+        assert_eq!(expressions(&calibrations[1]), vec!["3267 = 81 * 40 + 27"]);
+    }
+
+    #[test]
+    fn test_calibrations_all() {
+        let calibrations = calibrations(EXAMPLE, WITH_CONCAT, true);
+        assert_eq!(calibrations.len(), 6);
+        assert_eq!(expressions(&calibrations[1]), vec![
+            "3267 = 81 * 40 + 27",
+            "3267 = 81 + 40 * 27"
+        ]);
+        assert_eq!(expressions(&calibrations[3]), vec![
+            "7290 = 6 * 8 || 6 * 15"
+        ]);
+    }
+
+    #[test]
+    fn test_explain() {
+        assert_eq!(
+            explain(EXAMPLE, ARITHMETIC, true),
+            [
+                "190 = 10 * 19 (1 of 1)",
+                "3267 = 81 * 40 + 27 (1 of 2)",
+                "3267 = 81 + 40 * 27 (2 of 2)",
+                "292 = 11 + 6 * 16 + 20 (1 of 1)",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_parse_operator_set() {
+        let set: OperatorSet = "+, *,||,-,/,^,||2".parse().unwrap();
+        assert_eq!(symbols(&set.operators()), "+ * || - / ^ ||2");
+        assert!("%".parse::<OperatorSet>().is_err());
+        assert!("||1".parse::<OperatorSet>().is_err());
+        assert!("||x".parse::<OperatorSet>().is_err());
+        assert_eq!(total(EXAMPLE, &set.operators()[..3]), 11387);
+    }
+
+    const MAX: usize = u64::MAX as usize;
+
+    #[test]
+    fn test_apply_overflow() {
+        let concat = Concat { base: 10 };
+        assert_eq!(Add.apply(MAX - 1, 1), Some(MAX));
+        assert_eq!(Add.apply(MAX, 1), None);
+        assert_eq!(Multiply.apply(1 << 63, 2), None);
+        assert_eq!(Multiply.apply(1 << 32, 1 << 32), None);
+        assert_eq!(concat.apply(MAX / 10, 5), Some(MAX));
+        assert_eq!(concat.apply(MAX / 10, 6), None);
+        assert_eq!(concat.apply(MAX / 10 + 1, 0), None);
+        // The right value has 20 digits so nothing but zero fits in front of it
+        assert_eq!(concat.apply(0, MAX), Some(MAX));
+        assert_eq!(concat.apply(1, MAX), None);
+        assert_eq!(concat.inverse(MAX, MAX), Some(0));
+        assert_eq!(Subtract.inverse(MAX, 1), None);
+    }
+
+    #[test]
+    fn test_concat_zero() {
+        let concat = Concat { base: 10 };
+        assert_eq!(concat.apply(5, 0), Some(50));
+        assert_eq!(concat.apply(0, 5), Some(5));
+        assert_eq!(concat.apply(0, 0), Some(0));
+        assert_eq!(concat.inverse(50, 0), Some(5));
+        assert_eq!(concat.inverse(55, 0), None);
+        assert_eq!(Concat { base: 2 }.apply(1, 0), Some(2));
+    }
+
+    #[test]
+    fn test_solve_near_max() {
+        let solve = |values: &[usize], test_value, operators| {
+            solve(values, test_value, operators).map(|sequence| symbols(&sequence))
+        };
+        assert_eq!(solve(&[MAX - 1, 1], MAX, WITH_CONCAT).as_deref(), Some("+"));
+        assert_eq!(
+            solve(&[MAX / 10, 5], MAX, WITH_CONCAT).as_deref(),
+            Some("||")
+        );
+        assert_eq!(
+            solve(&[MAX / 2, 2, 1], MAX, WITH_CONCAT).as_deref(),
+            Some("* +")
+        );
+        // 2^32 * 2^32 wraps around to zero, which mustn't count as a solution
+        let operators: &[&dyn Operator] = &[&Multiply, &Divide];
+        assert_eq!(solve(&[1 << 32, 1 << 32], 0, operators), None);
+        assert_eq!(solve(&[1 << 32, 1 << 32, 3], 3, &[&Multiply, &Add]), None);
+    }
+
+    #[test]
+    fn test_solve_zero() {
+        assert_eq!(count_solutions(&[5, 0], 0, ARITHMETIC), 1);
+        assert_eq!(count_solutions(&[0, 0], 0, ARITHMETIC), 2);
+        assert_eq!(count_solutions(&[7, 0, 3], 3, ARITHMETIC), 1);
+        assert_eq!(count_solutions(&[1, 0, 2], 102, WITH_CONCAT), 1);
+        assert_eq!(count_solutions(&[0, 5], 5, WITH_CONCAT), 2);
+        assert_eq!(total("0: 5 0\n3: 7 0 3\n1: 1 0", ARITHMETIC), 4);
+    }
+
+    #[test]
+    fn test_evaluate_with_precedence() {
+        let concat_at = |precedence| WithPrecedence {
+            operator: Concat { base: 10 },
+            precedence,
+        };
+        assert_eq!(
+            evaluate_with_precedence(&[2, 3, 4], &[&Add, &Multiply]),
+            Some(14)
+        );
+        assert_eq!(
+            evaluate_with_precedence(&[2, 3, 4], &[&Multiply, &Add]),
+            Some(10)
+        );
+        assert_eq!(
+            evaluate_with_precedence(&[1, 2, 3, 4, 5], &[&Add, &Multiply, &Add, &Multiply]),
+            Some(27)
+        );
+        let (highest, additive) = (concat_at(CONCATENATION), concat_at(ADDITIVE));
+        assert_eq!(
+            evaluate_with_precedence(&[2, 3, 4], &[&Add, &highest]),
+            Some(36)
+        );
+        assert_eq!(
+            evaluate_with_precedence(&[2, 3, 4], &[&Add, &additive]),
+            Some(54)
+        );
+        assert_eq!(
+            evaluate_with_precedence(&[2, 3, 4], &[&highest, &Multiply]),
+            Some(92)
+        );
+        assert_eq!(
+            evaluate_with_precedence(&[2, 3, 4], &[&additive, &Multiply]),
+            Some(212)
+        );
+        assert_eq!(
+            evaluate_with_precedence(&[2, 3, 2], &[&Multiply, &Power]),
+            Some(18)
+        );
+        assert_eq!(evaluate_with_precedence(&[7], &[]), Some(7));
+        assert_eq!(
+            evaluate_with_precedence(&[1, MAX, 2], &[&Add, &Multiply]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_solve_with_precedence() {
+        let solve = |values: &[usize], test_value, operators| {
+            solve_with_precedence(values, test_value, operators).map(|sequence| symbols(&sequence))
+        };
+        assert_eq!(
+            solve(&[81, 40, 27], 3267, ARITHMETIC).as_deref(),
+            Some("* +")
+        );
+        // Left to right this is 11 + 6 * 16 + 20, but with precedence it is 127
+        assert_eq!(solve(&[11, 6, 16, 20], 292, ARITHMETIC), None);
+        assert_eq!(
+            solve(&[11, 6, 16, 20], 127, ARITHMETIC).as_deref(),
+            Some("+ * +")
+        );
+        assert_eq!(solve(&[7], 7, ARITHMETIC).as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_solve_with_precedence_matches_brute_force() {
+        let additive = WithPrecedence {
+            operator: Concat { base: 10 },
+            precedence: ADDITIVE,
+        };
+        let operator_sets: [&[&dyn Operator]; 3] =
+            [ARITHMETIC, WITH_CONCAT, &[&Add, &Multiply, &additive]];
+        for equation in EXAMPLE.lines().map(parse_equation) {
+            for operators in operator_sets {
+                let Equation { values, test_value } = &equation;
+                let brute_force = combinations(values.len() - 1, operators)
+                    .any(|combo| evaluate_with_precedence(values, &combo) == Some(*test_value));
+                let solved = solve_with_precedence(values, *test_value, operators);
+                assert_eq!(solved.is_some(), brute_force, "{equation:?}");
+                if let Some(sequence) = solved {
+                    assert_eq!(
+                        evaluate_with_precedence(values, &sequence),
+                        Some(*test_value)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_total_with_precedence() {
+        // 190 and 3267, then 156 = 15 || 6 and 192 = 17 || 8 + 14 with concatenation
+        assert_eq!(total_with_precedence(EXAMPLE, ARITHMETIC), 3457);
+        assert_eq!(total_with_precedence(EXAMPLE, WITH_CONCAT), 3805);
+        assert_eq!("add".parse(), Ok(ConcatPrecedence::Additive));
+        assert!("first".parse::<ConcatPrecedence>().is_err());
+    }
+
     #[test]
     fn test_evaluate() {
         let values = vec![1, 2, 3, 4];
-        let operators = vec![Operator::Add, Operator::Concat, Operator::Multiply];
-        assert_eq!(evaluate(&values, &operators), 33 * 4);
+        let operators: &[&dyn Operator] = &[&Add, &Concat { base: 10 }, &Multiply];
+        assert_eq!(evaluate(&values, operators), Some(33 * 4));
+        assert_eq!(evaluate(&[MAX, 1], &[&Add]), None);
+        assert_eq!(
+            evaluate(&[1, 0, 5], &[&Concat { base: 10 }, &Add]),
+            Some(15)
+        );
     }
 }
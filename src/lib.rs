@@ -55,14 +55,61 @@ where
     map_res(digit1, str::parse)(input)
 }
 
-fn transpose<T: Copy>(input: &[Vec<T>]) -> Vec<Vec<T>> {
-    let mut transposed = vec![Vec::with_capacity(input.len()); input[0].len()];
-    for row in input {
-        for (i, cell) in row.iter().enumerate() {
-            transposed[i].push(*cell);
+/// Lazily slide a window of `height` rows by `width` columns over the grid, moving it
+/// `col_stride` columns at a time along each row and `row_stride` rows at a time down the grid.
+/// The windows borrow from the grid, nothing is copied.
+pub fn windows_2d<T>(
+    grid: &[Vec<T>],
+    height: usize,
+    width: usize,
+    row_stride: usize,
+    col_stride: usize,
+) -> impl Iterator<Item = Window<'_, T>> {
+    assert!(
+        height > 0 && width > 0 && row_stride > 0 && col_stride > 0,
+        "Window size and stride must be positive"
+    );
+    let row_len = grid.first().map_or(0, Vec::len);
+    gen move {
+        if grid.len() < height || row_len < width {
+            return;
         }
+        for row in (0..=grid.len() - height).step_by(row_stride) {
+            for col in (0..=row_len - width).step_by(col_stride) {
+                yield Window {
+                    row,
+                    col,
+                    width,
+                    rows: &grid[row..row + height],
+                };
+            }
+        }
+    }
+}
+
+/// A view of a rectangular part of a grid
+#[derive(Debug, Clone, Copy)]
+pub struct Window<'a, T> {
+    /// The row of the grid at the top of the window
+    pub row: usize,
+    /// The column of the grid at the left of the window
+    pub col: usize,
+    width: usize,
+    rows: &'a [Vec<T>],
+}
+
+impl<'a, T> Window<'a, T> {
+    /// Iterate over the rows of the window
+    pub fn rows(&self) -> impl Iterator<Item = &'a [T]> {
+        let (col, width) = (self.col, self.width);
+        self.rows.iter().map(move |row| &row[col..col + width])
+    }
+
+    /// Get the cell at the row and column relative to the top left of the window
+    pub fn get(&self, row: usize, col: usize) -> &'a T {
+        assert!(col < self.width, "Column outside of window");
+        &self.rows[row][self.col + col]
     }
-    transposed
 }
 
 /// Utility struct to iterate over grids
@@ -73,7 +120,7 @@ pub struct Grid<'a, T> {
 }
 
 impl<'a, T> Grid<'a, T> {
-    fn new(rows: &'a [Vec<T>]) -> Self {
+    pub fn new(rows: &'a [Vec<T>]) -> Self {
         Self {
             rows,
             row_len: rows[0].len(),
@@ -81,35 +128,46 @@ impl<'a, T> Grid<'a, T> {
         }
     }
 
-    fn rows(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
+    pub fn rows(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
         self.rows.iter().map(|row| row.iter())
     }
 
-    fn row(&self, i: usize) -> &[T] {
+    pub fn row(&self, i: usize) -> &[T] {
         &self.rows[i]
     }
 
-    fn cols(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
+    pub fn cols(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
         (0..self.rows[0].len()).map(|i| self.col(i))
     }
 
-    fn col(&self, i: usize) -> impl Iterator<Item = &T> {
+    pub fn col(&self, i: usize) -> impl Iterator<Item = &T> {
         self.rows.iter().map(move |row| &row[i])
     }
 
-    fn diagonals(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
+    /// Lazily slide a window over the grid, see [`windows_2d`]
+    pub fn windows(
+        &self,
+        height: usize,
+        width: usize,
+        row_stride: usize,
+        col_stride: usize,
+    ) -> impl Iterator<Item = Window<'a, T>> {
+        windows_2d(self.rows, height, width, row_stride, col_stride)
+    }
+
+    pub fn diagonals(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
         (0..self.rows[0].len() + self.rows.len() - 1).map(|i| self.diagonal(i))
     }
 
-    fn diagonals_rev(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
+    pub fn diagonals_rev(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
         (0..self.rows[0].len() + self.rows.len() - 1).map(|i| self.diagonal_rev(i))
     }
 
-    fn diagonal(&self, i: usize) -> impl Iterator<Item = &T> {
+    pub fn diagonal(&self, i: usize) -> impl Iterator<Item = &T> {
         self.diagonal_inner(i, self.rows.iter())
     }
 
-    fn diagonal_rev(&self, i: usize) -> impl Iterator<Item = &T> {
+    pub fn diagonal_rev(&self, i: usize) -> impl Iterator<Item = &T> {
         self.diagonal_inner(i, self.rows.iter().rev())
     }
 
@@ -122,12 +180,12 @@ impl<'a, T> Grid<'a, T> {
             i < self.row_len + self.col_len - 1,
             "Invalid diagonal index. Should be less than row_len + col_len - 1"
         );
+        // The diagonal holds the cells whose row and column add up to `i`
         let skip = i.saturating_sub(self.row_len - 1);
-        let take = min(i + 1, self.row_len) - skip;
+        let take = min(i + 1, self.col_len) - skip;
         gen move {
-            for (i, row) in rows.skip(skip).take(take).enumerate() {
-                let row_index = take - i - 1 + skip;
-                yield &row[row_index];
+            for (row_index, row) in rows.enumerate().skip(skip).take(take) {
+                yield &row[i - row_index];
             }
         }
     }
@@ -136,7 +194,6 @@ impl<'a, T> Grid<'a, T> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use test::Bencher;
 
     #[test]
     fn test_grid_rows() {
@@ -223,4 +280,105 @@ mod tests {
             vec![&3]
         ]);
     }
+
+    #[test]
+    fn test_grid_diagonals_rectangular() {
+        let tall = vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9], vec![10, 11, 12]];
+        let grid = Grid::new(&tall);
+        let diagonals: Vec<Vec<&i32>> = grid.diagonals().map(|diag| diag.collect()).collect();
+        assert_eq!(diagonals, vec![
+            vec![&1],
+            vec![&2, &4],
+            vec![&3, &5, &7],
+            vec![&6, &8, &10],
+            vec![&9, &11],
+            vec![&12]
+        ]);
+        let diagonals: Vec<Vec<&i32>> = grid.diagonals_rev().map(|diag| diag.collect()).collect();
+        assert_eq!(diagonals, vec![
+            vec![&10],
+            vec![&11, &7],
+            vec![&12, &8, &4],
+            vec![&9, &5, &1],
+            vec![&6, &2],
+            vec![&3]
+        ]);
+
+        let wide = vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8]];
+        let grid = Grid::new(&wide);
+        let diagonals: Vec<Vec<&i32>> = grid.diagonals().map(|diag| diag.collect()).collect();
+        assert_eq!(diagonals, vec![
+            vec![&1],
+            vec![&2, &5],
+            vec![&3, &6],
+            vec![&4, &7],
+            vec![&8]
+        ]);
+    }
+
+    fn collect_windows<'a, T: Copy + 'a>(
+        windows: impl Iterator<Item = Window<'a, T>>,
+    ) -> Vec<Vec<Vec<T>>> {
+        windows
+            .map(|window| window.rows().map(<[T]>::to_vec).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_windows_2d() {
+        let input = vec![vec!['a', 'b', 'c'], vec!['d', 'e', 'f'], vec![
+            'g', 'h', 'i',
+        ]];
+        let expected = vec![
+            vec![vec!['a', 'b'], vec!['d', 'e']],
+            vec![vec!['b', 'c'], vec!['e', 'f']],
+            vec![vec!['d', 'e'], vec!['g', 'h']],
+            vec![vec!['e', 'f'], vec!['h', 'i']],
+        ];
+        assert_eq!(collect_windows(windows_2d(&input, 2, 2, 1, 1)), expected);
+    }
+
+    #[test]
+    fn test_windows_2d_rectangular() {
+        let input = vec![vec!['a', 'b', 'c'], vec!['d', 'e', 'f'], vec![
+            'g', 'h', 'i',
+        ]];
+        let expected = vec![vec![vec!['a', 'b', 'c'], vec!['d', 'e', 'f']], vec![
+            vec!['d', 'e', 'f'],
+            vec!['g', 'h', 'i'],
+        ]];
+        assert_eq!(collect_windows(windows_2d(&input, 2, 3, 1, 1)), expected);
+    }
+
+    #[test]
+    fn test_windows_2d_stride() {
+        let input: Vec<Vec<i32>> = (0..4)
+            .map(|r| (0..5).map(|c| r * 5 + c).collect())
+            .collect();
+        let positions: Vec<(usize, usize)> = windows_2d(&input, 2, 2, 2, 3)
+            .map(|window| (window.row, window.col))
+            .collect();
+        assert_eq!(positions, vec![(0, 0), (0, 3), (2, 0), (2, 3)]);
+        let window = windows_2d(&input, 2, 2, 2, 3).last().unwrap();
+        assert_eq!(*window.get(1, 1), 19);
+    }
+
+    #[test]
+    fn test_windows_2d_too_large() {
+        let input = vec![vec![1, 2], vec![3, 4]];
+        assert_eq!(windows_2d(&input, 3, 1, 1, 1).count(), 0);
+        assert_eq!(windows_2d(&input, 1, 3, 1, 1).count(), 0);
+        let empty: Vec<Vec<i32>> = vec![];
+        assert_eq!(windows_2d(&empty, 1, 1, 1, 1).count(), 0);
+    }
+
+    #[test]
+    fn test_grid_windows() {
+        let grid = vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]];
+        let grid = Grid::new(&grid);
+        assert_eq!(collect_windows(grid.windows(3, 1, 1, 2)), vec![
+            vec![vec![1], vec![4], vec![7]],
+            vec![vec![3], vec![6], vec![9]],
+        ]);
+    }
 }
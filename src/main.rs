@@ -2,6 +2,7 @@ use aoc_2024::{
     day1, day10, day2, day3, day4, day5, day6, day7, day8, day9, read_input
 };
 use clap::Parser;
+use std::{io::Write, thread, time::Duration};
 use tikv_jemallocator::Jemalloc;
 
 #[global_allocator]
@@ -13,11 +14,147 @@ struct Args {
     day: u8,
     #[arg(short, long)]
     part: u8,
+    /// Print how the answer was reached for each line of the input
+    #[arg(long)]
+    explain: bool,
+    /// Run day 2 with custom safety rules, e.g. `min=1,max=3,plateaus,trend=increasing`,
+    /// and explain the verdict for each report
+    #[arg(long)]
+    policy: Option<day2::SafetyPolicy>,
+    /// List every instruction executed or skipped by day 3 with its offset in the input
+    #[arg(long)]
+    trace: bool,
+    /// Print the day 4 grid showing only the letters that are part of a match
+    #[arg(long)]
+    render: bool,
+    /// Colour the rendered day 4 matches by direction
+    #[arg(long, requires = "render")]
+    colour: bool,
+    /// Animate the day 6 guard's patrol in the terminal
+    #[arg(long)]
+    visualize: bool,
+    /// Milliseconds between frames of the animation
+    #[arg(long, requires = "visualize", default_value_t = 50)]
+    speed: u64,
+    /// Replay the day 6 patrol with an obstruction at `row,col`
+    #[arg(long, requires = "visualize")]
+    obstruction: Option<day6::Position>,
+    /// Print the day 6 patrol, its turns and the obstructions that cause loops as JSON
+    #[arg(long)]
+    json: bool,
+    /// Count the tiles the day 6 guards visit under custom movement rules,
+    /// e.g. `turn=left,wrap,one-way,guards`
+    #[arg(long)]
+    movement: Option<day6::MovementPolicy>,
+    /// Explain every satisfying day 7 expression instead of just the first
+    #[arg(long, requires = "explain")]
+    all: bool,
+    /// Solve day 7 with a custom set of operators, e.g. `+,*,||,-,/,^,||2`
+    #[arg(long)]
+    operators: Option<day7::OperatorSet>,
+    /// Solve day 7 part 2 with multiplication before addition instead of from left to right,
+    /// with concatenation at the precedence of `add`, `multiply` or `highest`
+    #[arg(long, conflicts_with = "operators")]
+    precedence: Option<day7::ConcatPrecedence>,
 }
 
 fn main() {
     let args = Args::parse();
     let input = read_input(args.day);
+    if args.trace {
+        assert_eq!(args.day, 3, "--trace is only supported for day 3");
+        let instructions = if args.part == 2 {
+            day3::CONDITIONAL
+        } else {
+            day3::BASIC
+        };
+        print!("{}", day3::trace(&input, instructions));
+    }
+    if args.render {
+        assert_eq!(args.day, 4, "--render is only supported for day 4");
+        print!("{}", day4::render_matches(&input, args.part, args.colour));
+    }
+    if args.visualize {
+        assert_eq!(args.day, 6, "--visualize is only supported for day 6");
+        let mut stdout = std::io::stdout();
+        for frame in day6::visualize(&input, args.part, args.obstruction) {
+            // Clear the screen and draw the frame from the top left
+            println!("\x1b[2J\x1b[H{frame}");
+            stdout.flush().unwrap();
+            thread::sleep(Duration::from_millis(args.speed));
+        }
+        return;
+    }
+    if args.json {
+        assert_eq!(args.day, 6, "--json is only supported for day 6");
+        let patrol = day6::patrol(&input).unwrap_or_else(|e| panic!("{e}"));
+        println!("{}", patrol.to_json());
+        return;
+    }
+    if let Some(policy) = args.movement {
+        assert_eq!(
+            (args.day, args.part),
+            (6, 1),
+            "--movement is only supported for day 6 part 1"
+        );
+        let solution = day6::solution(&input, &policy).unwrap_or_else(|e| panic!("{e}"));
+        println!("{solution}");
+        return;
+    }
+    if let Some(concat) = args.precedence {
+        assert_eq!(
+            (args.day, args.part),
+            (7, 2),
+            "--precedence is only supported for day 7 part 2"
+        );
+        let concat = day7::WithPrecedence {
+            operator: day7::Concat { base: 10 },
+            precedence: concat.level(),
+        };
+        let operators: [&dyn day7::Operator; 3] = [&day7::Add, &day7::Multiply, &concat];
+        println!("{}", day7::total_with_precedence(&input, &operators));
+        return;
+    }
+    if let Some(set) = &args.operators {
+        assert_eq!(args.day, 7, "--operators is only supported for day 7");
+        if !args.explain {
+            println!("{}", day7::total(&input, &set.operators()));
+            return;
+        }
+    }
+    if args.policy.is_some() {
+        assert_eq!(args.day, 2, "--policy is only supported for day 2");
+    }
+    if args.explain || args.policy.is_some() {
+        let solution = match args.day {
+            2 => {
+                let policy = args.policy.unwrap_or_default();
+                let tolerance = usize::from(args.part == 2);
+                print!("{}", day2::explain(&input, &policy, tolerance));
+                day2::solution(&input, &policy, tolerance)
+            }
+            5 => {
+                print!("{}", day5::explain(&input));
+                if args.part == 2 {
+                    day5::part_2(&input)
+                } else {
+                    day5::part_1(&input)
+                }
+            }
+            7 => {
+                let operators = match &args.operators {
+                    Some(set) => set.operators(),
+                    None if args.part == 2 => day7::WITH_CONCAT.to_vec(),
+                    None => day7::ARITHMETIC.to_vec(),
+                };
+                print!("{}", day7::explain(&input, &operators, args.all));
+                day7::total(&input, &operators)
+            }
+            _ => panic!("Explaining day {} is not implemented", args.day),
+        };
+        println!("{solution}");
+        return;
+    }
     let solution = match (args.day, args.part) {
         (1, 1) => day1::part_1(&input),
         (1, 2) => day1::part_2(&input),